@@ -3,10 +3,11 @@ use std::{env, fs, path::Path};
 
 fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
-    let dest_path = Path::new(&out_dir).join("index.html");
-    let page = include_bytes!("./static/index.html");
-    let minified = minify_html::minify(page, &Cfg::spec_compliant());
-    fs::write(dest_path, minified).expect("failed to save minified index page");
+    for page in ["index.html", "stats.html", "embed.html"] {
+        let source = fs::read(Path::new("static").join(page)).expect("failed to read page");
+        let minified = minify_html::minify(&source, &Cfg::spec_compliant());
+        fs::write(Path::new(&out_dir).join(page), minified).expect("failed to save minified page");
+    }
 
     println!("cargo:rerun-if-changed=static");
 }
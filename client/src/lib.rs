@@ -0,0 +1,274 @@
+use crate::models::*;
+use reqwest::{Client, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use std::fmt::{self, Display, Formatter};
+
+pub mod models;
+
+pub use reqwest;
+
+/// The header used to pass an API key, which the faucet accepts in place of a captcha.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug)]
+pub enum FaucetClientError {
+    /// The faucet rejected the request.
+    Api {
+        status: StatusCode,
+        code: ErrorCode,
+        error: String,
+    },
+    /// The faucet couldn't be reached, or responded with something unexpected.
+    Http(reqwest::Error),
+    InvalidUrl(String),
+}
+
+impl FaucetClientError {
+    /// The code returned by the faucet, if it rejected the request.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            FaucetClientError::Api { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for FaucetClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FaucetClientError::Api {
+                status,
+                code,
+                error,
+            } => write!(f, "{status} {code:?}: {error}"),
+            FaucetClientError::Http(e) => write!(f, "{e}"),
+            FaucetClientError::InvalidUrl(url) => write!(f, "invalid faucet url: {url}"),
+        }
+    }
+}
+
+impl std::error::Error for FaucetClientError {}
+
+impl From<reqwest::Error> for FaucetClientError {
+    fn from(e: reqwest::Error) -> Self {
+        FaucetClientError::Http(e)
+    }
+}
+
+/// A typed client for the faucet's `/v1` HTTP API.
+#[derive(Debug, Clone)]
+pub struct FaucetClient {
+    client: Client,
+    url: Url,
+    api_key: Option<String>,
+    session: Option<String>,
+}
+
+impl FaucetClient {
+    pub fn new(url: &str) -> Result<Self, FaucetClientError> {
+        Self::with_client(url, Client::new())
+    }
+
+    pub fn with_client(url: &str, client: Client) -> Result<Self, FaucetClientError> {
+        let mut url =
+            Url::parse(url).map_err(|_| FaucetClientError::InvalidUrl(url.to_string()))?;
+        // make sure paths are joined onto the base url rather than replacing its last segment
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        Ok(Self {
+            client,
+            url,
+            api_key: None,
+            session: None,
+        })
+    }
+
+    /// Authenticates dispenses with an API key, so no captcha is needed.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Dispenses as a signed in user, with the session token the faucet issued after signing in.
+    pub fn with_session(mut self, session: impl Into<String>) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    pub async fn info(&self) -> Result<DispenseInfoResponse, FaucetClientError> {
+        let response = self
+            .client
+            .get(self.endpoint("v1/dispense")?)
+            .send()
+            .await?;
+        parse(response).await
+    }
+
+    pub async fn dispense(
+        &self,
+        address: &str,
+        captcha: &str,
+    ) -> Result<DispenseResponse, FaucetClientError> {
+        self.dispense_to(RecipientType::Address, address, captcha)
+            .await
+    }
+
+    /// Funds a deployed contract with the base asset.
+    pub async fn dispense_to_contract(
+        &self,
+        contract_id: &str,
+        captcha: &str,
+    ) -> Result<DispenseResponse, FaucetClientError> {
+        self.dispense_to(RecipientType::Contract, contract_id, captcha)
+            .await
+    }
+
+    pub async fn dispense_to(
+        &self,
+        recipient_type: RecipientType,
+        address: &str,
+        captcha: &str,
+    ) -> Result<DispenseResponse, FaucetClientError> {
+        self.dispense_with(&DispenseInput {
+            address: address.to_string(),
+            captcha: captcha.to_string(),
+            recipient_type,
+            amount: None,
+            proof: None,
+            redirect_url: None,
+        })
+        .await
+    }
+
+    /// Sends a dispense request, e.g. one asking for a specific amount.
+    pub async fn dispense_with(
+        &self,
+        input: &DispenseInput,
+    ) -> Result<DispenseResponse, FaucetClientError> {
+        let mut request = self.client.post(self.endpoint("v1/dispense")?).json(input);
+        if let Some(api_key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        if let Some(session) = &self.session {
+            request = request.bearer_auth(session);
+        }
+        let response = request.send().await?;
+        parse(response).await
+    }
+
+    /// Requests a nonce to sign with the key of `address`, to prove ownership of it.
+    pub async fn nonce(&self, address: &str) -> Result<NonceResponse, FaucetClientError> {
+        let response = self
+            .client
+            .get(self.endpoint(&format!("v1/dispense/nonce/{address}"))?)
+            .send()
+            .await?;
+        parse(response).await
+    }
+
+    pub async fn eligibility(
+        &self,
+        address: &str,
+    ) -> Result<EligibilityResponse, FaucetClientError> {
+        self.eligibility_of(RecipientType::Address, address).await
+    }
+
+    pub async fn eligibility_of(
+        &self,
+        recipient_type: RecipientType,
+        address: &str,
+    ) -> Result<EligibilityResponse, FaucetClientError> {
+        let response = self
+            .client
+            .get(self.endpoint(&format!("v1/dispense/eligibility/{address}"))?)
+            .query(&[("recipient_type", recipient_type)])
+            .send()
+            .await?;
+        parse(response).await
+    }
+
+    /// Returns a page of the past dispenses to the address, newest first. Pass the `next_cursor`
+    /// of a page to get the one after it.
+    pub async fn history(
+        &self,
+        address: &str,
+        cursor: Option<u64>,
+    ) -> Result<DispenseHistoryResponse, FaucetClientError> {
+        self.history_of(RecipientType::Address, address, cursor)
+            .await
+    }
+
+    pub async fn history_of(
+        &self,
+        recipient_type: RecipientType,
+        address: &str,
+        cursor: Option<u64>,
+    ) -> Result<DispenseHistoryResponse, FaucetClientError> {
+        let mut request = self
+            .client
+            .get(self.endpoint(&format!("v1/dispense/history/{address}"))?)
+            .query(&[("recipient_type", recipient_type)]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        let response = request.send().await?;
+        parse(response).await
+    }
+
+    /// Returns the faucet's public statistics, which may be cached for a while.
+    pub async fn stats(&self) -> Result<StatsResponse, FaucetClientError> {
+        let response = self.client.get(self.endpoint("v1/stats")?).send().await?;
+        parse(response).await
+    }
+
+    /// Returns the clusters found by the faucet's sybil detection, which requires an API key.
+    pub async fn sybil_report(&self) -> Result<SybilReport, FaucetClientError> {
+        let mut request = self.client.get(self.endpoint("v1/admin/sybil")?);
+        if let Some(api_key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        let response = request.send().await?;
+        parse(response).await
+    }
+
+    /// Takes the address off the sybil denylist for good, which requires an API key. Returns
+    /// the report once the address is unlisted.
+    pub async fn sybil_unlist(&self, address: &str) -> Result<SybilReport, FaucetClientError> {
+        let mut request = self
+            .client
+            .delete(self.endpoint(&format!("v1/admin/sybil/{address}"))?);
+        if let Some(api_key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        let response = request.send().await?;
+        parse(response).await
+    }
+
+    /// Returns the faucet's health. An unhealthy faucet still responds with a health report, so
+    /// this only fails if the faucet couldn't be reached.
+    pub async fn health(&self) -> Result<HealthResponse, FaucetClientError> {
+        let response = self.client.get(self.endpoint("v1/health")?).send().await?;
+        Ok(response.json().await?)
+    }
+
+    fn endpoint(&self, path: &str) -> Result<Url, FaucetClientError> {
+        self.url
+            .join(path)
+            .map_err(|_| FaucetClientError::InvalidUrl(format!("{}{path}", self.url)))
+    }
+}
+
+async fn parse<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, FaucetClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response.json().await?);
+    }
+
+    let ErrorResponse { code, error } = response.json().await?;
+    Err(FaucetClientError::Api {
+        status,
+        code,
+        error,
+    })
+}
@@ -0,0 +1,383 @@
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HealthResponse {
+    pub up: bool,
+    /// Milliseconds since the faucet started
+    pub uptime: u64,
+    #[serde(rename = "fuel-core")]
+    pub fuel_core: bool,
+    /// The wallets the faucet dispenses from
+    #[serde(default)]
+    pub wallets: Vec<WalletBalance>,
+    /// How tight the rate limits currently are
+    #[serde(default)]
+    pub rate_limit_mode: RateLimitMode,
+}
+
+/// The faucet tightens its limits while traffic is above its baseline: dispenses are smaller,
+/// budgets last longer, and everyone but API key holders has to solve a captcha.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitMode {
+    #[default]
+    Normal,
+    Elevated,
+    Strict,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WalletBalance {
+    pub address: String,
+    /// Base asset balance, if it could be fetched from the node
+    pub balance: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DispenseInfoResponse {
+    /// The amount dispensed when a request doesn't ask for a specific amount
+    pub amount: u64,
+    pub asset_id: String,
+    /// The largest amount a single request may ask for
+    #[serde(default)]
+    pub max_amount: u64,
+    /// The total amount each recipient may receive per interval
+    #[serde(default)]
+    pub budget: u64,
+    /// The length of the interval, in seconds
+    #[serde(default)]
+    pub interval: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DispenseInput {
+    /// Recipient address or contract id, either hex or bech32 encoded
+    pub address: String,
+    pub captcha: String,
+    #[serde(default)]
+    pub recipient_type: RecipientType,
+    /// The amount to dispense, up to the max amount. Defaults to the faucet's dispense amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+    /// Proof that the requester owns the recipient address, which raises its budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<OwnershipProof>,
+    /// Where to send the user once the dispense is done. Ignored unless its origin is allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+}
+
+/// A nonce issued by `GET /v1/dispense/nonce/{address}`, signed with the key of the address.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OwnershipProof {
+    pub nonce: String,
+    /// The hex encoded signature of the nonce, signed as a message like any other
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NonceResponse {
+    /// The message to sign with the key of the address
+    pub nonce: String,
+    /// How long the nonce can be used for, in seconds
+    pub expires_in: u64,
+}
+
+/// Whether funds are dispensed to an address or to a contract.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RecipientType {
+    #[default]
+    Address,
+    Contract,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DispenseResponse {
+    pub status: String,
+    pub tokens: u64,
+    pub tx_id: String,
+    /// Link to the transaction on the block explorer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    /// The recipient's balance after the dispense, absent if it couldn't be looked up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<u64>,
+    /// The requested redirect target with the `tx_id` query param, if its origin is allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum DispenseStatus {
+    Committed,
+    Failed,
+}
+
+/// A past dispense to a recipient.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DispenseRecord {
+    /// Absent if the dispense failed before its transaction was submitted
+    pub tx_id: Option<String>,
+    pub amount: u64,
+    /// When the dispense completed, in seconds since the unix epoch
+    pub timestamp: u64,
+    pub status: DispenseStatus,
+    /// Why the dispense failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DispenseHistoryResponse {
+    /// The dispenses to the recipient, newest first
+    pub records: Vec<DispenseRecord>,
+    /// The cursor of the next page of older dispenses, absent on the last page
+    pub next_cursor: Option<u64>,
+}
+
+/// The dispenses committed within a time bucket.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StatsBucket {
+    /// The start of the bucket, in seconds since the unix epoch
+    pub start: u64,
+    pub dispenses: u64,
+    pub amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StatsResponse {
+    /// The total amount dispensed
+    pub total_dispensed: u64,
+    /// The number of committed dispenses
+    pub dispenses: u64,
+    pub unique_recipients: u64,
+    /// The dispenses of each of the last 24 hours, oldest first
+    pub hourly: Vec<StatsBucket>,
+    /// The dispenses of each of the last 30 days, oldest first
+    pub daily: Vec<StatsBucket>,
+    /// The combined balance of the faucet wallets, absent if the node is unreachable
+    pub balance: Option<u64>,
+    /// When the statistics were computed, in seconds since the unix epoch
+    pub generated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EligibilityResponse {
+    pub eligible: bool,
+    /// Why the address can't receive assets right now, if it isn't eligible
+    pub code: Option<ErrorCode>,
+    /// The amount the address may still receive in the current interval. An address with less
+    /// than the default dispense amount remaining isn't eligible, but may still request up to
+    /// this amount.
+    #[serde(default)]
+    pub remaining: u64,
+}
+
+/// Recipients that forwarded their dispensed funds to a common collector.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SybilCluster {
+    pub collector: String,
+    pub feeders: Vec<String>,
+    /// The total amount forwarded to the collector by the feeders
+    pub amount: u64,
+    /// When the cluster was first detected, in seconds since the unix epoch
+    pub detected_at: u64,
+    /// When the cluster is dropped unless the collector is still being fed, in seconds since the
+    /// unix epoch
+    pub expires_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SybilReport {
+    pub clusters: Vec<SybilCluster>,
+    /// Every address that is refused because of the clusters
+    pub denylisted: Vec<String>,
+    /// Addresses unlisted by an admin, which are never denylisted again
+    #[serde(default)]
+    pub unlisted: Vec<String>,
+    /// When the last analysis completed, in seconds since the unix epoch
+    pub analyzed_at: Option<u64>,
+}
+
+/// Faucet activity, streamed by `GET /v1/events` as server-sent events named after the type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FaucetEvent {
+    /// A dispense transaction was submitted to the node
+    DispenseSubmitted {
+        /// The recipient, truncated in the public feed
+        recipient: String,
+        tx_id: String,
+        amount: u64,
+    },
+    /// A dispense transaction was committed
+    DispenseCommitted {
+        recipient: String,
+        tx_id: String,
+        amount: u64,
+    },
+    /// A dispense failed after passing the checks of the request
+    DispenseFailed {
+        recipient: String,
+        amount: u64,
+        code: ErrorCode,
+    },
+    /// The balance of a faucet wallet changed
+    BalanceChanged { wallet: String, balance: u64 },
+}
+
+impl FaucetEvent {
+    /// The name of the server-sent event.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FaucetEvent::DispenseSubmitted { .. } => "dispense_submitted",
+            FaucetEvent::DispenseCommitted { .. } => "dispense_committed",
+            FaucetEvent::DispenseFailed { .. } => "dispense_failed",
+            FaucetEvent::BalanceChanged { .. } => "balance_changed",
+        }
+    }
+}
+
+/// The body of the requests sent to webhooks, signed with the webhook secret.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookPayload {
+    /// Unique to the event, and the same across retries of its delivery
+    pub id: String,
+    /// When the event happened, in seconds since the unix epoch
+    pub created_at: u64,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    DispenseCommitted {
+        recipient: String,
+        tx_id: String,
+        amount: u64,
+    },
+    DispenseFailed {
+        recipient: String,
+        amount: u64,
+        code: ErrorCode,
+    },
+    /// The balance of a faucet wallet fell below the low balance threshold
+    LowBalance {
+        wallet: String,
+        balance: u64,
+        threshold: u64,
+    },
+}
+
+/// The body of every failed request.
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    pub error: String,
+}
+
+/// Stable, machine-readable identifier for each kind of failure, so clients don't have to
+/// match on the human-readable message. Codes added after a client was built are read as
+/// `Unknown`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidAddress,
+    InvalidAmount,
+    CaptchaFailed,
+    LoginFailed,
+    AccountTooNew,
+    InvalidProof,
+    ProofRequired,
+    ApiKeyRequired,
+    Denylisted,
+    NetworkBlocked,
+    RateLimited,
+    InProgress,
+    InsufficientFunds,
+    SubmitFailed,
+    SubmitTimeout,
+    ShuttingDown,
+    RequestTimeout,
+    Overloaded,
+    InternalError,
+    /// A code this client doesn't know of, which the faucet never sends itself
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// The status the faucet responds with for this error.
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidAddress | ErrorCode::InvalidAmount => StatusCode::BAD_REQUEST,
+            ErrorCode::CaptchaFailed
+            | ErrorCode::LoginFailed
+            | ErrorCode::InvalidProof
+            | ErrorCode::ProofRequired
+            | ErrorCode::ApiKeyRequired => StatusCode::UNAUTHORIZED,
+            ErrorCode::AccountTooNew | ErrorCode::Denylisted | ErrorCode::NetworkBlocked => {
+                StatusCode::FORBIDDEN
+            }
+            ErrorCode::RateLimited | ErrorCode::InProgress => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::RequestTimeout => StatusCode::REQUEST_TIMEOUT,
+            ErrorCode::ShuttingDown | ErrorCode::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::InsufficientFunds
+            | ErrorCode::SubmitFailed
+            | ErrorCode::SubmitTimeout
+            | ErrorCode::InternalError
+            | ErrorCode::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// The message the faucet responds with for this error. Internal details are only logged
+    /// by the faucet, never sent back.
+    pub fn message(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidAddress => "invalid address",
+            ErrorCode::InvalidAmount => "invalid amount",
+            ErrorCode::CaptchaFailed => "captcha failed",
+            ErrorCode::LoginFailed => "Sign in failed",
+            ErrorCode::AccountTooNew => "The account is too new to sign in",
+            ErrorCode::InvalidProof => "The proof of address ownership is invalid",
+            ErrorCode::ProofRequired => "A proof of address ownership is required",
+            ErrorCode::ApiKeyRequired => "A valid API key is required",
+            ErrorCode::Denylisted => "The address isn't allowed to receive assets",
+            ErrorCode::NetworkBlocked => "Requests from this network aren't allowed",
+            ErrorCode::RateLimited => "Account has already received assets today",
+            ErrorCode::InProgress => "Account is already in the process of receiving assets",
+            ErrorCode::InsufficientFunds => "The faucet doesn't have enough funds to dispense",
+            ErrorCode::SubmitFailed => "Failed to submit transaction",
+            ErrorCode::SubmitTimeout => "Timed out waiting for the transaction to be committed",
+            ErrorCode::ShuttingDown => "Faucet is shutting down, try again later",
+            ErrorCode::RequestTimeout => "request timed out",
+            ErrorCode::Overloaded => "service is overloaded, try again later",
+            ErrorCode::InternalError => "Unhandled internal error",
+            ErrorCode::Unknown => "Unknown error",
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Adaptive rate limiting. The controller watches the rate of dispense requests and the share of
+//! them that get rejected, and tightens the configured limits while either is above its baseline.
+//! It escalates as soon as traffic is anomalous, and relaxes one mode at a time once traffic has
+//! been calm for the cooldown.
+
+use crate::{config::Config, dispense_tracker::Clock, models::RateLimitMode};
+use reqwest::StatusCode;
+use std::{collections::VecDeque, sync::Mutex};
+
+/// The window over which the request rate is measured, in seconds.
+const WINDOW: u64 = 60;
+/// The minimum number of requests in the window before the rejection ratio counts.
+const MIN_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Dispensed,
+    /// Refused because of the request, e.g. rate limited or a failed captcha
+    Rejected,
+    /// Failed on the faucet's side
+    Failed,
+}
+
+/// The number of dispense requests by outcome since the faucet started.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Totals {
+    pub dispensed: u64,
+    pub rejected: u64,
+    pub failed: u64,
+}
+
+/// The requests received within a second.
+#[derive(Debug)]
+struct Second {
+    at: u64,
+    requests: usize,
+    rejected: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    /// The requests within the window, by second, oldest first
+    window: VecDeque<Second>,
+    /// The running totals of the window, so that checking it doesn't walk every request
+    requests: usize,
+    rejected: usize,
+    mode: RateLimitMode,
+    /// The last time traffic justified the current mode
+    last_anomaly: u64,
+    totals: Totals,
+}
+
+/// The limits in effect, tightened from the configured ones according to the mode.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub dispense_amount: u64,
+    pub max_dispense_amount: u64,
+    pub dispense_budget: u64,
+    pub verified_dispense_budget: u64,
+    pub identity_dispense_budget: u64,
+    pub interval: u64,
+    /// Whether signed in users have to solve the captcha as well
+    pub captcha_required: bool,
+}
+
+impl Limits {
+    pub fn new(config: &Config, mode: RateLimitMode) -> Self {
+        let factor = match mode {
+            RateLimitMode::Normal => 1,
+            RateLimitMode::Elevated => 2,
+            RateLimitMode::Strict => 4,
+        };
+        Self {
+            dispense_amount: (config.dispense_amount / factor).max(1),
+            max_dispense_amount: (config.max_dispense_amount / factor).max(1),
+            dispense_budget: config.dispense_budget / factor,
+            verified_dispense_budget: config.verified_dispense_budget / factor,
+            identity_dispense_budget: config.identity_dispense_budget / factor,
+            interval: config.dispense_limit_interval * factor,
+            captcha_required: mode != RateLimitMode::Normal,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AdaptiveLimiter {
+    enabled: bool,
+    /// Dispense requests per minute considered normal
+    baseline: usize,
+    max_rejection_ratio: f64,
+    cooldown: u64,
+    state: Mutex<State>,
+    clock: Box<dyn Clock>,
+}
+
+impl AdaptiveLimiter {
+    pub fn new(config: &Config, clock: impl Clock + 'static) -> Self {
+        Self {
+            enabled: config.adaptive_rate_limiting,
+            baseline: config.adaptive_baseline,
+            max_rejection_ratio: config.adaptive_max_rejection_ratio,
+            cooldown: config.adaptive_cooldown,
+            state: Mutex::new(State {
+                window: VecDeque::new(),
+                requests: 0,
+                rejected: 0,
+                mode: RateLimitMode::Normal,
+                last_anomaly: clock.now(),
+                totals: Totals::default(),
+            }),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// Records the response to a dispense request.
+    pub fn record(&self, status: StatusCode) {
+        let outcome = if status.is_success() {
+            Outcome::Dispensed
+        } else if status.is_client_error() {
+            Outcome::Rejected
+        } else {
+            Outcome::Failed
+        };
+
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        match outcome {
+            Outcome::Dispensed => state.totals.dispensed += 1,
+            Outcome::Rejected => state.totals.rejected += 1,
+            Outcome::Failed => state.totals.failed += 1,
+        }
+        if self.enabled {
+            let rejected = usize::from(outcome == Outcome::Rejected);
+            match state.window.back_mut() {
+                Some(second) if second.at == now => {
+                    second.requests += 1;
+                    second.rejected += rejected;
+                }
+                _ => state.window.push_back(Second {
+                    at: now,
+                    requests: 1,
+                    rejected,
+                }),
+            }
+            state.requests += 1;
+            state.rejected += rejected;
+            self.update(&mut state, now);
+        }
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        let mut state = self.state.lock().unwrap();
+        self.update(&mut state, self.clock.now());
+        state.mode
+    }
+
+    pub fn limits(&self, config: &Config) -> Limits {
+        Limits::new(config, self.mode())
+    }
+
+    pub fn totals(&self) -> Totals {
+        self.state.lock().unwrap().totals
+    }
+
+    /// The number of dispense requests within the last minute.
+    pub fn rate(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        self.update(&mut state, self.clock.now());
+        state.requests
+    }
+
+    fn update(&self, state: &mut State, now: u64) {
+        while state
+            .window
+            .front()
+            .is_some_and(|second| now.saturating_sub(second.at) >= WINDOW)
+        {
+            let second = state.window.pop_front().expect("checked above");
+            state.requests -= second.requests;
+            state.rejected -= second.rejected;
+        }
+
+        let (rate, rejected) = (state.requests, state.rejected);
+        let abusive =
+            rate >= MIN_SAMPLES && rejected as f64 / rate as f64 > self.max_rejection_ratio;
+        let target = if rate > self.baseline * 4 || (rate > self.baseline && abusive) {
+            RateLimitMode::Strict
+        } else if rate > self.baseline || abusive {
+            RateLimitMode::Elevated
+        } else {
+            RateLimitMode::Normal
+        };
+
+        if target >= state.mode {
+            state.mode = target;
+            state.last_anomaly = now;
+        } else if now.saturating_sub(state.last_anomaly) >= self.cooldown {
+            state.mode = match state.mode {
+                RateLimitMode::Strict => RateLimitMode::Elevated,
+                _ => RateLimitMode::Normal,
+            };
+            state.last_anomaly = now;
+        }
+    }
+}
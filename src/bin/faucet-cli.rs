@@ -0,0 +1,116 @@
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand};
+use fuel_crypto::{Message, SecretKey, Signature};
+use fuel_faucet::Recipient;
+use fuel_faucet_client::{
+    models::{DispenseInput, OwnershipProof, RecipientType},
+    FaucetClient,
+};
+use std::str::FromStr;
+
+/// Request funds from a fuel faucet
+#[derive(Parser, Debug)]
+#[command(name = "faucet-cli")]
+struct Cli {
+    /// The url of the faucet
+    #[arg(long, env = "FAUCET_URL", default_value = "http://127.0.0.1:3000")]
+    faucet_url: String,
+    /// An API key accepted by the faucet in place of a captcha
+    #[arg(long, env = "FAUCET_API_KEY")]
+    api_key: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the amount and asset dispensed by the faucet
+    Info,
+    /// Request funds for an address or a contract
+    Dispense {
+        /// The recipient, either hex or bech32 encoded
+        address: String,
+        /// Fund the contract with this id rather than an address
+        #[arg(long)]
+        contract: bool,
+        /// The amount to request, defaults to the faucet's dispense amount
+        #[arg(long)]
+        amount: Option<u64>,
+        /// The secret key of the address, to prove ownership of it for a larger budget
+        #[arg(long, env = "RECIPIENT_SECRET_KEY", hide_env_values = true)]
+        secret_key: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let mut faucet = FaucetClient::new(&cli.faucet_url)?;
+    if let Some(api_key) = cli.api_key {
+        faucet = faucet.with_api_key(api_key);
+    }
+
+    match cli.command {
+        Command::Info => {
+            let info = faucet.info().await?;
+            println!("amount: {}", info.amount);
+            println!("asset id: {}", info.asset_id);
+        }
+        Command::Dispense {
+            address,
+            contract,
+            amount,
+            secret_key,
+        } => {
+            let recipient_type = if contract {
+                RecipientType::Contract
+            } else {
+                RecipientType::Address
+            };
+            let recipient = Recipient::parse(&address, recipient_type)
+                .ok_or_else(|| anyhow!("invalid {recipient_type:?} recipient"))?;
+            let id = match recipient {
+                Recipient::Address(address) => format!("{address:#x}"),
+                Recipient::Contract(contract_id) => format!("{contract_id:#x}"),
+            };
+            let proof = match secret_key {
+                Some(secret_key) => {
+                    if contract {
+                        bail!("contracts can't prove ownership");
+                    }
+                    let secret_key = SecretKey::from_str(&secret_key)
+                        .map_err(|e| anyhow!("invalid secret key: {e}"))?;
+                    let nonce = faucet.nonce(&id).await?.nonce;
+                    let signature = Signature::sign(&secret_key, &Message::new(&nonce));
+                    Some(OwnershipProof {
+                        nonce,
+                        signature: format!("{signature:x}"),
+                    })
+                }
+                None => None,
+            };
+            let response = faucet
+                .dispense_with(&DispenseInput {
+                    address: id,
+                    captcha: String::new(),
+                    recipient_type,
+                    amount,
+                    proof,
+                    redirect_url: None,
+                })
+                .await?;
+            println!("tx id: {}", response.tx_id);
+            println!("amount: {}", response.tokens);
+            // the faucet responds once the transaction is committed
+            if let Some(balance) = response.balance {
+                println!("balance: {balance}");
+            }
+            if let Some(explorer_url) = &response.explorer_url {
+                println!("explorer: {explorer_url}");
+            }
+        }
+    }
+
+    Ok(())
+}
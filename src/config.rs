@@ -1,11 +1,30 @@
 use crate::constants::{
-    CAPTCHA_KEY, CAPTCHA_SECRET, DEFAULT_DISPENSE_INTERVAL, DEFAULT_FAUCET_DISPENSE_AMOUNT,
-    DEFAULT_NODE_URL, DEFAULT_NUMBER_OF_RETRIES, DEFAULT_PORT, DISPENSE_AMOUNT, DISPENSE_INTERVAL,
-    FUEL_NODE_URL, HUMAN_LOGGING, LOG_FILTER, NUMBER_OF_RETRIES, PUBLIC_FUEL_NODE_URL,
-    SERVICE_PORT, TIMEOUT_SECONDS, WALLET_SECRET_KEY,
+    ADAPTIVE_BASELINE, ADAPTIVE_COOLDOWN_SECONDS, ADAPTIVE_MAX_REJECTION_RATIO,
+    ADAPTIVE_RATE_LIMITING, API_KEYS, CAPTCHA_KEY, CAPTCHA_SECRET, DEFAULT_ADAPTIVE_BASELINE,
+    DEFAULT_ADAPTIVE_COOLDOWN, DEFAULT_ADAPTIVE_MAX_REJECTION_RATIO, DEFAULT_DISPENSE_INTERVAL,
+    DEFAULT_EXPLORER_TX_URL, DEFAULT_FAUCET_DISPENSE_AMOUNT, DEFAULT_GITHUB_AUTHORIZE_URL,
+    DEFAULT_GITHUB_TOKEN_URL, DEFAULT_GITHUB_USER_URL, DEFAULT_IP_POLICY_RELOAD_INTERVAL,
+    DEFAULT_MIN_ACCOUNT_AGE_DAYS, DEFAULT_NODE_URL, DEFAULT_NUMBER_OF_RETRIES, DEFAULT_PORT,
+    DEFAULT_SHUTDOWN_TIMEOUT, DEFAULT_STATS_CACHE_DURATION, DEFAULT_SYBIL_ANALYSIS_INTERVAL,
+    DEFAULT_SYBIL_DENYLIST_DURATION, DEFAULT_SYBIL_MIN_CLUSTER_SIZE, DEFAULT_SYBIL_TRACKING_PERIOD,
+    DEFAULT_WEBHOOK_MAX_ATTEMPTS, DISPENSE_AMOUNT, DISPENSE_BUDGET, DISPENSE_HISTORY_FILE,
+    DISPENSE_INTERVAL, EMBED_ALLOWED_ORIGINS, EXPLORER_TX_URL, FUEL_NODE_URL, GITHUB_AUTHORIZE_URL,
+    GITHUB_CLIENT_ID, GITHUB_CLIENT_SECRET, GITHUB_TOKEN_URL, GITHUB_USER_URL, HUMAN_LOGGING,
+    IDENTITY_DISPENSE_BUDGET, IP_ASN_DATABASES, IP_POLICY_FILE, IP_POLICY_RELOAD_SECONDS,
+    LOG_FILTER, LOW_BALANCE_THRESHOLD, MAX_DISPENSE_AMOUNT, MIN_ACCOUNT_AGE_DAYS,
+    NUMBER_OF_RETRIES, OAUTH_REDIRECT_URL, PUBLIC_FUEL_NODE_URL, REDIRECT_ALLOWED_ORIGINS,
+    REMOTE_SIGNER_ADDRESS, REMOTE_SIGNER_TOKEN, REMOTE_SIGNER_TOKEN_FILE, REMOTE_SIGNER_URL,
+    REQUIRE_OWNERSHIP_PROOF, SERVICE_PORT, SHUTDOWN_TIMEOUT_SECONDS, STATS_CACHE_SECONDS,
+    SYBIL_ANALYSIS_INTERVAL_SECONDS, SYBIL_DENYLIST_SECONDS, SYBIL_DETECTION,
+    SYBIL_IGNORED_ADDRESSES, SYBIL_MIN_CLUSTER_SIZE, SYBIL_TRACKING_SECONDS, TEMPLATE_DIR,
+    TEMPLATE_VAR_PREFIX, TIMEOUT_SECONDS, TRUSTED_PROXY_COUNT, USE_DEV_WALLET,
+    VERIFIED_DISPENSE_BUDGET, WALLET_KEYSTORE, WALLET_KEYSTORE_PASSWORD,
+    WALLET_KEYSTORE_PASSWORD_FILE, WALLET_SECRET_KEY, WALLET_SECRET_KEYS, WALLET_SECRET_KEY_FILE,
+    WEBHOOK_DEAD_LETTER_FILE, WEBHOOK_MAX_ATTEMPTS, WEBHOOK_SECRET, WEBHOOK_SECRET_FILE,
+    WEBHOOK_URLS,
 };
 use secrecy::Secret;
-use std::env;
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -14,17 +33,128 @@ pub struct Config {
     pub service_port: u16,
     pub captcha_key: Option<String>,
     pub captcha_secret: Option<Secret<String>>,
+    pub api_keys: Vec<Secret<String>>,
+    /// GitHub OAuth app, which lets users sign in for a larger budget
+    pub github_client_id: Option<String>,
+    pub github_client_secret: Option<Secret<String>>,
+    pub github_authorize_url: String,
+    pub github_token_url: String,
+    pub github_user_url: String,
+    /// The callback url registered with the OAuth app, if it has several
+    pub oauth_redirect_url: Option<String>,
+    /// How old an account has to be to sign in
+    pub min_account_age_days: u64,
+    /// The total amount each signed in account may receive per `dispense_limit_interval`
+    pub identity_dispense_budget: u64,
+    /// Only dispense to addresses whose ownership has been proven
+    pub require_ownership_proof: bool,
+    /// The total amount each address may receive per `dispense_limit_interval` when its
+    /// ownership has been proven
+    pub verified_dispense_budget: u64,
+    /// Follow dispensed funds and denylist recipients that forward them to a common collector
+    pub sybil_detection: bool,
+    /// How often the dispensed funds are followed, in seconds
+    pub sybil_analysis_interval: u64,
+    /// How many recipients have to forward funds to an address for it to count as a collector
+    pub sybil_min_cluster_size: usize,
+    /// How long recipients are followed after their dispense, in seconds
+    pub sybil_tracking_period: u64,
+    /// How long a cluster stays denylisted after its collector was last fed, in seconds
+    pub sybil_denylist_duration: u64,
+    /// Well-known addresses many honest recipients pay into, e.g. exchanges and bridges, which
+    /// are never considered collectors
+    pub sybil_ignored_addresses: Vec<String>,
+    /// Tighten the limits while dispense traffic is above the baseline
+    pub adaptive_rate_limiting: bool,
+    /// The number of dispense requests per minute considered normal
+    pub adaptive_baseline: usize,
+    /// The share of rejected dispense requests per minute considered normal
+    pub adaptive_max_rejection_ratio: f64,
+    /// How long traffic has to be calm before the limits are relaxed by a step, in seconds
+    pub adaptive_cooldown: u64,
+    /// Rules for the networks requests come from, reloaded when the file changes
+    pub ip_policy_file: Option<PathBuf>,
+    /// CSV files mapping network ranges to autonomous systems, for the ASN rules
+    pub ip_asn_databases: Vec<PathBuf>,
+    /// How often the IP policy files are checked for changes, in seconds
+    pub ip_policy_reload_interval: u64,
+    /// The number of reverse proxies in front of the faucet, each appending the address it
+    /// received the request from to the `X-Forwarded-For` header
+    pub trusted_proxy_count: usize,
+    /// File the dispense history is kept in, as JSON lines. Kept in memory only if unset.
+    pub dispense_history_file: Option<PathBuf>,
+    /// How long the public statistics are cached for, in seconds
+    pub stats_cache_duration: u64,
+    /// Link to a dispense transaction, with `{tx_id}` standing for its id. Empty for no link.
+    pub explorer_tx_url: Option<String>,
+    /// Directory of templates overriding the embedded pages
+    pub template_dir: Option<PathBuf>,
+    /// Variables available to the templates, such as `network_name` or `logo_url`
+    pub template_vars: BTreeMap<String, String>,
+    /// Origins allowed to frame the embeddable widget, besides the faucet itself
+    pub embed_allowed_origins: Vec<String>,
+    /// Origins the page can redirect users back to once they received their funds
+    pub redirect_allowed_origins: Vec<String>,
+    /// Endpoints notified of dispenses and low balances
+    pub webhook_urls: Vec<String>,
+    /// Key of the HMAC signature of the webhook payloads
+    pub webhook_secret: Option<Secret<String>>,
+    /// How many times delivering to a webhook is attempted before it's dead-lettered
+    pub webhook_max_attempts: u32,
+    /// File that undeliverable webhook payloads are appended to, as JSON lines
+    pub webhook_dead_letter_file: Option<PathBuf>,
+    /// Balance below which the webhooks are notified that a wallet runs low
+    pub low_balance_threshold: Option<u64>,
     pub node_url: String,
     pub public_node_url: String,
     pub wallet_secret_key: Option<Secret<String>>,
+    /// Keys of additional wallets to dispense from alongside the main wallet
+    pub wallet_secret_keys: Vec<Secret<String>>,
+    /// Path to an encrypted JSON keystore holding the wallet key, used if no secret key is set
+    pub wallet_keystore: Option<PathBuf>,
+    pub wallet_keystore_password: Option<Secret<String>>,
+    /// Fall back to the public development wallet if no wallet is configured
+    pub use_dev_wallet: bool,
+    /// Base URL of an external signing service holding the wallet key, used instead of a local key
+    pub remote_signer_url: Option<String>,
+    /// Address of the key held by the remote signer
+    pub remote_signer_address: Option<String>,
+    /// Bearer token sent to the remote signer
+    pub remote_signer_token: Option<Secret<String>>,
+    /// The amount dispensed when a request doesn't ask for a specific amount
     pub dispense_amount: u64,
+    /// The largest amount a single request may ask for
+    pub max_dispense_amount: u64,
+    /// The total amount each recipient may receive per `dispense_limit_interval`
+    pub dispense_budget: u64,
     pub number_of_retries: u64,
     pub dispense_limit_interval: u64,
     pub timeout: u64,
+    pub shutdown_timeout: u64,
 }
 
 impl Default for Config {
     fn default() -> Self {
+        let dispense_amount = env::var(DISPENSE_AMOUNT)
+            .unwrap_or_else(|_| DEFAULT_FAUCET_DISPENSE_AMOUNT.to_string())
+            .parse::<u64>()
+            .expect("expected a valid integer for DISPENSE_AMOUNT");
+        let max_dispense_amount = env::var(MAX_DISPENSE_AMOUNT)
+            .map(|amount| {
+                amount
+                    .parse::<u64>()
+                    .expect("expected a valid integer for MAX_DISPENSE_AMOUNT")
+            })
+            .unwrap_or(dispense_amount);
+        // by default, a single dispense of the max amount per interval
+        let dispense_budget = env::var(DISPENSE_BUDGET)
+            .map(|budget| {
+                budget
+                    .parse::<u64>()
+                    .expect("expected a valid integer for DISPENSE_BUDGET")
+            })
+            .unwrap_or(max_dispense_amount);
+
         Self {
             log_filter: env::var(LOG_FILTER).unwrap_or_default(),
             human_logging: parse_bool(HUMAN_LOGGING, true),
@@ -34,15 +164,170 @@ impl Default for Config {
             captcha_secret: env::var_os(CAPTCHA_SECRET)
                 .map(|s| Secret::new(s.into_string().unwrap())),
             captcha_key: env::var_os(CAPTCHA_KEY).map(|s| s.into_string().unwrap()),
+            api_keys: env::var(API_KEYS)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|key| !key.is_empty())
+                .map(|key| Secret::new(key.to_string()))
+                .collect(),
+            github_client_id: env::var(GITHUB_CLIENT_ID).ok(),
+            github_client_secret: env::var(GITHUB_CLIENT_SECRET).ok().map(Secret::new),
+            github_authorize_url: env::var(GITHUB_AUTHORIZE_URL)
+                .unwrap_or_else(|_| DEFAULT_GITHUB_AUTHORIZE_URL.to_string()),
+            github_token_url: env::var(GITHUB_TOKEN_URL)
+                .unwrap_or_else(|_| DEFAULT_GITHUB_TOKEN_URL.to_string()),
+            github_user_url: env::var(GITHUB_USER_URL)
+                .unwrap_or_else(|_| DEFAULT_GITHUB_USER_URL.to_string()),
+            oauth_redirect_url: env::var(OAUTH_REDIRECT_URL).ok(),
+            min_account_age_days: env::var(MIN_ACCOUNT_AGE_DAYS)
+                .unwrap_or_else(|_| DEFAULT_MIN_ACCOUNT_AGE_DAYS.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for MIN_ACCOUNT_AGE_DAYS"),
+            identity_dispense_budget: env::var(IDENTITY_DISPENSE_BUDGET)
+                .map(|budget| {
+                    budget
+                        .parse::<u64>()
+                        .expect("expected a valid integer for IDENTITY_DISPENSE_BUDGET")
+                })
+                .unwrap_or(dispense_budget * 5),
+            require_ownership_proof: parse_bool(REQUIRE_OWNERSHIP_PROOF, false),
+            verified_dispense_budget: env::var(VERIFIED_DISPENSE_BUDGET)
+                .map(|budget| {
+                    budget
+                        .parse::<u64>()
+                        .expect("expected a valid integer for VERIFIED_DISPENSE_BUDGET")
+                })
+                .unwrap_or(dispense_budget * 2),
+            sybil_detection: parse_bool(SYBIL_DETECTION, false),
+            sybil_analysis_interval: env::var(SYBIL_ANALYSIS_INTERVAL_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_SYBIL_ANALYSIS_INTERVAL.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for SYBIL_ANALYSIS_INTERVAL_SECONDS"),
+            sybil_min_cluster_size: env::var(SYBIL_MIN_CLUSTER_SIZE)
+                .unwrap_or_else(|_| DEFAULT_SYBIL_MIN_CLUSTER_SIZE.to_string())
+                .parse::<usize>()
+                .expect("expected a valid integer for SYBIL_MIN_CLUSTER_SIZE"),
+            sybil_tracking_period: env::var(SYBIL_TRACKING_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_SYBIL_TRACKING_PERIOD.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for SYBIL_TRACKING_SECONDS"),
+            sybil_denylist_duration: env::var(SYBIL_DENYLIST_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_SYBIL_DENYLIST_DURATION.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for SYBIL_DENYLIST_SECONDS"),
+            sybil_ignored_addresses: env::var(SYBIL_IGNORED_ADDRESSES)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(str::to_string)
+                .collect(),
+            adaptive_rate_limiting: parse_bool(ADAPTIVE_RATE_LIMITING, false),
+            adaptive_baseline: env::var(ADAPTIVE_BASELINE)
+                .unwrap_or_else(|_| DEFAULT_ADAPTIVE_BASELINE.to_string())
+                .parse::<usize>()
+                .expect("expected a valid integer for ADAPTIVE_BASELINE"),
+            adaptive_max_rejection_ratio: env::var(ADAPTIVE_MAX_REJECTION_RATIO)
+                .unwrap_or_else(|_| DEFAULT_ADAPTIVE_MAX_REJECTION_RATIO.to_string())
+                .parse::<f64>()
+                .expect("expected a valid number for ADAPTIVE_MAX_REJECTION_RATIO"),
+            adaptive_cooldown: env::var(ADAPTIVE_COOLDOWN_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_ADAPTIVE_COOLDOWN.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for ADAPTIVE_COOLDOWN_SECONDS"),
+            ip_policy_file: env::var_os(IP_POLICY_FILE).map(PathBuf::from),
+            ip_asn_databases: env::var(IP_ASN_DATABASES)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            ip_policy_reload_interval: env::var(IP_POLICY_RELOAD_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_IP_POLICY_RELOAD_INTERVAL.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for IP_POLICY_RELOAD_SECONDS"),
+            trusted_proxy_count: env::var(TRUSTED_PROXY_COUNT)
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<usize>()
+                .expect("expected a valid integer for TRUSTED_PROXY_COUNT"),
+            dispense_history_file: env::var_os(DISPENSE_HISTORY_FILE).map(PathBuf::from),
+            stats_cache_duration: env::var(STATS_CACHE_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_STATS_CACHE_DURATION.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for STATS_CACHE_SECONDS"),
+            explorer_tx_url: Some(
+                env::var(EXPLORER_TX_URL).unwrap_or_else(|_| DEFAULT_EXPLORER_TX_URL.to_string()),
+            )
+            .filter(|url| !url.is_empty()),
+            template_dir: env::var_os(TEMPLATE_DIR).map(PathBuf::from),
+            template_vars: env::vars()
+                .filter_map(|(name, value)| {
+                    let name = name.strip_prefix(TEMPLATE_VAR_PREFIX)?;
+                    Some((name.to_lowercase(), value))
+                })
+                .collect(),
+            embed_allowed_origins: env::var(EMBED_ALLOWED_ORIGINS)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|origin| !origin.is_empty())
+                .map(|origin| {
+                    reqwest::Url::parse(origin.trim())
+                        .map(|url| url.origin().ascii_serialization())
+                        .expect("expected valid origins for EMBED_ALLOWED_ORIGINS")
+                })
+                .collect(),
+            redirect_allowed_origins: env::var(REDIRECT_ALLOWED_ORIGINS)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|origin| !origin.is_empty())
+                .map(|origin| {
+                    reqwest::Url::parse(origin.trim())
+                        .map(|url| url.origin().ascii_serialization())
+                        .expect("expected valid origins for REDIRECT_ALLOWED_ORIGINS")
+                })
+                .collect(),
+            webhook_urls: env::var(WEBHOOK_URLS)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect(),
+            webhook_secret: secret_from_env_or_file(WEBHOOK_SECRET, WEBHOOK_SECRET_FILE),
+            webhook_max_attempts: env::var(WEBHOOK_MAX_ATTEMPTS)
+                .unwrap_or_else(|_| DEFAULT_WEBHOOK_MAX_ATTEMPTS.to_string())
+                .parse::<u32>()
+                .expect("expected a valid integer for WEBHOOK_MAX_ATTEMPTS"),
+            webhook_dead_letter_file: env::var_os(WEBHOOK_DEAD_LETTER_FILE).map(PathBuf::from),
+            low_balance_threshold: env::var(LOW_BALANCE_THRESHOLD).ok().map(|threshold| {
+                threshold
+                    .parse::<u64>()
+                    .expect("expected a valid integer for LOW_BALANCE_THRESHOLD")
+            }),
             node_url: env::var(FUEL_NODE_URL).unwrap_or_else(|_| DEFAULT_NODE_URL.to_string()),
             public_node_url: env::var(PUBLIC_FUEL_NODE_URL)
                 .unwrap_or_else(|_| DEFAULT_NODE_URL.to_string()),
-            wallet_secret_key: env::var_os(WALLET_SECRET_KEY)
-                .map(|s| Secret::new(s.into_string().unwrap())),
-            dispense_amount: env::var(DISPENSE_AMOUNT)
-                .unwrap_or_else(|_| DEFAULT_FAUCET_DISPENSE_AMOUNT.to_string())
-                .parse::<u64>()
-                .expect("expected a valid integer for DISPENSE_AMOUNT"),
+            wallet_secret_key: secret_from_env_or_file(WALLET_SECRET_KEY, WALLET_SECRET_KEY_FILE),
+            wallet_secret_keys: env::var(WALLET_SECRET_KEYS)
+                .unwrap_or_default()
+                .split(',')
+                .filter(|key| !key.is_empty())
+                .map(|key| Secret::new(key.to_string()))
+                .collect(),
+            wallet_keystore: env::var_os(WALLET_KEYSTORE).map(PathBuf::from),
+            wallet_keystore_password: secret_from_env_or_file(
+                WALLET_KEYSTORE_PASSWORD,
+                WALLET_KEYSTORE_PASSWORD_FILE,
+            ),
+            use_dev_wallet: parse_bool(USE_DEV_WALLET, false),
+            remote_signer_url: env::var(REMOTE_SIGNER_URL).ok(),
+            remote_signer_address: env::var(REMOTE_SIGNER_ADDRESS).ok(),
+            remote_signer_token: secret_from_env_or_file(
+                REMOTE_SIGNER_TOKEN,
+                REMOTE_SIGNER_TOKEN_FILE,
+            ),
+            dispense_amount,
+            max_dispense_amount,
+            dispense_budget,
             number_of_retries: env::var(NUMBER_OF_RETRIES)
                 .unwrap_or_else(|_| DEFAULT_NUMBER_OF_RETRIES.to_string())
                 .parse::<u64>()
@@ -55,6 +340,10 @@ impl Default for Config {
                 .unwrap_or_else(|_| "10".to_string())
                 .parse::<u64>()
                 .expect("expected a valid integer for TIMEOUT_SECONDS"),
+            shutdown_timeout: env::var(SHUTDOWN_TIMEOUT_SECONDS)
+                .unwrap_or_else(|_| DEFAULT_SHUTDOWN_TIMEOUT.to_string())
+                .parse::<u64>()
+                .expect("expected a valid integer for SHUTDOWN_TIMEOUT_SECONDS"),
         }
     }
 }
@@ -68,3 +357,17 @@ fn parse_bool(env_var: &str, default: bool) -> bool {
         })
         .unwrap_or(default)
 }
+
+/// Reads a secret from `env_var`, or from the file at the path given by `file_env_var`
+/// (e.g. a mounted k8s secret).
+fn secret_from_env_or_file(env_var: &str, file_env_var: &str) -> Option<Secret<String>> {
+    if let Some(secret) = env::var_os(env_var) {
+        return Some(Secret::new(secret.into_string().unwrap()));
+    }
+
+    env::var_os(file_env_var).map(|path| {
+        let secret = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Unable to read `{file_env_var}` at {path:?}: {e}"));
+        Secret::new(secret.trim().to_string())
+    })
+}
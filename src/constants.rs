@@ -2,13 +2,80 @@ pub const LOG_FILTER: &str = "RUST_LOG";
 pub const HUMAN_LOGGING: &str = "HUMAN_LOGGING";
 pub const CAPTCHA_KEY: &str = "CAPTCHA_KEY";
 pub const CAPTCHA_SECRET: &str = "CAPTCHA_SECRET";
+pub const API_KEYS: &str = "API_KEYS";
+pub const GITHUB_CLIENT_ID: &str = "GITHUB_CLIENT_ID";
+pub const GITHUB_CLIENT_SECRET: &str = "GITHUB_CLIENT_SECRET";
+pub const GITHUB_AUTHORIZE_URL: &str = "GITHUB_AUTHORIZE_URL";
+pub const DEFAULT_GITHUB_AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+pub const GITHUB_TOKEN_URL: &str = "GITHUB_TOKEN_URL";
+pub const DEFAULT_GITHUB_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+pub const GITHUB_USER_URL: &str = "GITHUB_USER_URL";
+pub const DEFAULT_GITHUB_USER_URL: &str = "https://api.github.com/user";
+pub const OAUTH_REDIRECT_URL: &str = "OAUTH_REDIRECT_URL";
+pub const MIN_ACCOUNT_AGE_DAYS: &str = "MIN_ACCOUNT_AGE_DAYS";
+pub const DEFAULT_MIN_ACCOUNT_AGE_DAYS: u64 = 30;
+pub const IDENTITY_DISPENSE_BUDGET: &str = "IDENTITY_DISPENSE_BUDGET";
+pub const REQUIRE_OWNERSHIP_PROOF: &str = "REQUIRE_OWNERSHIP_PROOF";
+pub const VERIFIED_DISPENSE_BUDGET: &str = "VERIFIED_DISPENSE_BUDGET";
+pub const SYBIL_DETECTION: &str = "SYBIL_DETECTION";
+pub const SYBIL_ANALYSIS_INTERVAL_SECONDS: &str = "SYBIL_ANALYSIS_INTERVAL_SECONDS";
+pub const DEFAULT_SYBIL_ANALYSIS_INTERVAL: u64 = 5 * 60;
+pub const SYBIL_MIN_CLUSTER_SIZE: &str = "SYBIL_MIN_CLUSTER_SIZE";
+pub const DEFAULT_SYBIL_MIN_CLUSTER_SIZE: usize = 5;
+pub const SYBIL_TRACKING_SECONDS: &str = "SYBIL_TRACKING_SECONDS";
+pub const DEFAULT_SYBIL_TRACKING_PERIOD: u64 = 7 * 24 * 60 * 60;
+pub const SYBIL_DENYLIST_SECONDS: &str = "SYBIL_DENYLIST_SECONDS";
+pub const DEFAULT_SYBIL_DENYLIST_DURATION: u64 = 7 * 24 * 60 * 60;
+pub const SYBIL_IGNORED_ADDRESSES: &str = "SYBIL_IGNORED_ADDRESSES";
+pub const ADAPTIVE_RATE_LIMITING: &str = "ADAPTIVE_RATE_LIMITING";
+pub const ADAPTIVE_BASELINE: &str = "ADAPTIVE_BASELINE";
+pub const DEFAULT_ADAPTIVE_BASELINE: usize = 60;
+pub const ADAPTIVE_MAX_REJECTION_RATIO: &str = "ADAPTIVE_MAX_REJECTION_RATIO";
+pub const DEFAULT_ADAPTIVE_MAX_REJECTION_RATIO: f64 = 0.5;
+pub const ADAPTIVE_COOLDOWN_SECONDS: &str = "ADAPTIVE_COOLDOWN_SECONDS";
+pub const DEFAULT_ADAPTIVE_COOLDOWN: u64 = 10 * 60;
+pub const IP_POLICY_FILE: &str = "IP_POLICY_FILE";
+pub const IP_ASN_DATABASES: &str = "IP_ASN_DATABASES";
+pub const IP_POLICY_RELOAD_SECONDS: &str = "IP_POLICY_RELOAD_SECONDS";
+pub const DEFAULT_IP_POLICY_RELOAD_INTERVAL: u64 = 30;
+pub const TRUSTED_PROXY_COUNT: &str = "TRUSTED_PROXY_COUNT";
+pub const DISPENSE_HISTORY_FILE: &str = "DISPENSE_HISTORY_FILE";
+pub const STATS_CACHE_SECONDS: &str = "STATS_CACHE_SECONDS";
+pub const DEFAULT_STATS_CACHE_DURATION: u64 = 60;
+pub const EXPLORER_TX_URL: &str = "EXPLORER_TX_URL";
+pub const DEFAULT_EXPLORER_TX_URL: &str = "https://app-testnet.fuel.network/tx/{tx_id}";
+pub const TEMPLATE_DIR: &str = "TEMPLATE_DIR";
+/// Env vars starting with this prefix set template variables, e.g. `TEMPLATE_VAR_NETWORK_NAME`
+/// sets `network_name`.
+pub const TEMPLATE_VAR_PREFIX: &str = "TEMPLATE_VAR_";
+pub const EMBED_ALLOWED_ORIGINS: &str = "EMBED_ALLOWED_ORIGINS";
+pub const REDIRECT_ALLOWED_ORIGINS: &str = "REDIRECT_ALLOWED_ORIGINS";
+pub const WEBHOOK_URLS: &str = "WEBHOOK_URLS";
+pub const WEBHOOK_SECRET: &str = "WEBHOOK_SECRET";
+pub const WEBHOOK_SECRET_FILE: &str = "WEBHOOK_SECRET_FILE";
+pub const WEBHOOK_MAX_ATTEMPTS: &str = "WEBHOOK_MAX_ATTEMPTS";
+pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+pub const WEBHOOK_DEAD_LETTER_FILE: &str = "WEBHOOK_DEAD_LETTER_FILE";
+pub const LOW_BALANCE_THRESHOLD: &str = "LOW_BALANCE_THRESHOLD";
 pub const WALLET_SECRET_KEY: &str = "WALLET_SECRET_KEY";
+pub const WALLET_SECRET_KEY_FILE: &str = "WALLET_SECRET_KEY_FILE";
+pub const WALLET_SECRET_KEYS: &str = "WALLET_SECRET_KEYS";
+pub const WALLET_KEYSTORE: &str = "WALLET_KEYSTORE";
+pub const WALLET_KEYSTORE_PASSWORD: &str = "WALLET_KEYSTORE_PASSWORD";
+pub const WALLET_KEYSTORE_PASSWORD_FILE: &str = "WALLET_KEYSTORE_PASSWORD_FILE";
+pub const USE_DEV_WALLET: &str = "USE_DEV_WALLET";
+pub const REMOTE_SIGNER_URL: &str = "REMOTE_SIGNER_URL";
+pub const REMOTE_SIGNER_ADDRESS: &str = "REMOTE_SIGNER_ADDRESS";
+pub const REMOTE_SIGNER_TOKEN: &str = "REMOTE_SIGNER_TOKEN";
+pub const REMOTE_SIGNER_TOKEN_FILE: &str = "REMOTE_SIGNER_TOKEN_FILE";
 pub const PUBLIC_FUEL_NODE_URL: &str = "PUBLIC_FUEL_NODE_URL";
 pub const WALLET_SECRET_DEV_KEY: &str =
     "99ad179d4f892ff3124ccd817408ff8a4452d9c16bb1b4968b8a59797e13cd7a";
 pub const FUEL_NODE_URL: &str = "FUEL_NODE_URL";
 pub const DEFAULT_NODE_URL: &str = "http://127.0.0.1:4000";
 pub const DISPENSE_AMOUNT: &str = "DISPENSE_AMOUNT";
+pub const MAX_DISPENSE_AMOUNT: &str = "MAX_DISPENSE_AMOUNT";
+pub const DISPENSE_BUDGET: &str = "DISPENSE_BUDGET";
 pub const NUMBER_OF_RETRIES: &str = "NUMBER_OF_RETRIES";
 pub const DISPENSE_INTERVAL: &str = "DISPENSE_LIMIT_INTERVAL";
 pub const DEFAULT_DISPENSE_INTERVAL: u64 = 24 * 60 * 60;
@@ -18,8 +85,12 @@ pub const SERVICE_PORT: &str = "PORT";
 pub const DEFAULT_PORT: u16 = 3000;
 
 pub const TIMEOUT_SECONDS: &str = "TIMEOUT_SECONDS";
+pub const SHUTDOWN_TIMEOUT_SECONDS: &str = "SHUTDOWN_TIMEOUT_SECONDS";
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
 
 // HTTP config
 
 /// The max number of simultaneous requests that can be buffered until backpressure is applied
 pub const MAX_CONCURRENT_REQUESTS: usize = 1024usize;
+/// How many events are buffered for subscribers of `/events` that fall behind.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
@@ -1,13 +1,21 @@
 use std::collections::BTreeMap;
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use fuel_types::Address;
+use crate::Recipient;
+use std::fmt::{self, Display, Formatter};
 
 pub trait Clock: std::fmt::Debug + Send + Sync {
     fn now(&self) -> u64;
 }
 
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
 #[derive(Debug)]
 pub struct StdTime {}
 
@@ -21,18 +29,37 @@ impl Clock for StdTime {
     }
 }
 
+/// What a budget is accounted against: the recipient for anonymous dispenses, or the account
+/// of a signed in user.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    Recipient(Recipient),
+    Account(String),
+}
+
+impl Display for RateLimitKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitKey::Recipient(recipient) => write!(f, "{recipient}"),
+            RateLimitKey::Account(account) => write!(f, "account {account}"),
+        }
+    }
+}
+
+/// Keeps track of the amount dispensed to each recipient within the last interval, so that
+/// recipients share a token budget rather than a number of dispenses.
 #[derive(Debug)]
 pub struct DispenseTracker {
-    tracked: HashMap<Address, u64>,
-    queue: BTreeMap<u64, Vec<Address>>,
-    in_progress: HashSet<Address>,
+    spent: HashMap<RateLimitKey, u64>,
+    queue: BTreeMap<u64, Vec<(RateLimitKey, u64)>>,
+    in_progress: HashSet<RateLimitKey>,
     clock: Box<dyn Clock>,
 }
 
 impl Default for DispenseTracker {
     fn default() -> Self {
         Self {
-            tracked: HashMap::default(),
+            spent: HashMap::default(),
             queue: Default::default(),
             in_progress: HashSet::default(),
             clock: Box::new(StdTime {}),
@@ -43,27 +70,27 @@ impl Default for DispenseTracker {
 impl DispenseTracker {
     pub fn new(clock: impl Clock + 'static) -> Self {
         Self {
-            tracked: HashMap::new(),
+            spent: HashMap::new(),
             queue: Default::default(),
             in_progress: HashSet::new(),
             clock: Box::new(clock),
         }
     }
 
-    pub fn track(&mut self, address: Address) {
-        self.in_progress.remove(&address);
+    pub fn track(&mut self, key: RateLimitKey, amount: u64) {
+        self.in_progress.remove(&key);
 
         let timestamp = self.clock.now();
-        self.tracked.insert(address, timestamp);
-        self.queue.entry(timestamp).or_default().push(address);
+        *self.spent.entry(key.clone()).or_default() += amount;
+        self.queue.entry(timestamp).or_default().push((key, amount));
     }
 
-    pub fn mark_in_progress(&mut self, address: Address) {
-        self.in_progress.insert(address);
+    pub fn mark_in_progress(&mut self, key: RateLimitKey) {
+        self.in_progress.insert(key);
     }
 
-    pub fn remove_in_progress(&mut self, address: &Address) {
-        self.in_progress.remove(address);
+    pub fn remove_in_progress(&mut self, key: &RateLimitKey) {
+        self.in_progress.remove(key);
     }
 
     pub fn evict_expired_entries(&mut self, eviction_duration: u64) {
@@ -71,10 +98,15 @@ impl DispenseTracker {
 
         while let Some(oldest_entry) = self.queue.first_entry() {
             if now - oldest_entry.key() > eviction_duration {
-                let (_, addresses) = oldest_entry.remove_entry();
-
-                for address in addresses {
-                    self.tracked.remove(&address);
+                let (_, dispenses) = oldest_entry.remove_entry();
+
+                for (key, amount) in dispenses {
+                    if let Entry::Occupied(mut spent) = self.spent.entry(key) {
+                        *spent.get_mut() = spent.get().saturating_sub(amount);
+                        if *spent.get() == 0 {
+                            spent.remove();
+                        }
+                    }
                 }
             } else {
                 break;
@@ -82,11 +114,12 @@ impl DispenseTracker {
         }
     }
 
-    pub fn has_tracked(&self, address: &Address) -> bool {
-        self.tracked.contains_key(address)
+    /// The amount dispensed within the current interval.
+    pub fn spent(&self, key: &RateLimitKey) -> u64 {
+        self.spent.get(key).copied().unwrap_or_default()
     }
 
-    pub fn is_in_progress(&self, address: &Address) -> bool {
-        self.in_progress.contains(address)
+    pub fn is_in_progress(&self, key: &RateLimitKey) -> bool {
+        self.in_progress.contains(key)
     }
 }
@@ -0,0 +1,379 @@
+//! History of the dispenses to each recipient, so that users can check what they received.
+//! Records are appended to the history file as JSON lines by a dedicated thread, and streamed
+//! back on startup. Only the latest records of the recipients dispensed to most recently are
+//! kept in memory, older pages are read from the file.
+
+use crate::{
+    models::{DispenseRecord, DispenseStatus, ErrorCode, RecipientType},
+    Recipient,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap, VecDeque},
+    fs::{File, OpenOptions},
+    hash::BuildHasher,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::PathBuf,
+    sync::{mpsc, Mutex},
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info, warn};
+
+/// The number of records per page, unless the request asks for another.
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+/// The largest number of records per page.
+pub const MAX_PAGE_SIZE: usize = 100;
+/// How many records of each recipient are kept in memory. Older ones remain in the file.
+const MAX_RECORDS_PER_RECIPIENT: usize = 1000;
+/// How many recipients have their records kept in memory, the least recently dispensed to are
+/// evicted first.
+const MAX_CACHED_RECIPIENTS: usize = 10_000;
+const HOUR: u64 = 60 * 60;
+/// How long the hourly counts of the committed dispenses are kept for the statistics.
+const HOURLY_RETENTION: u64 = 31 * 24 * HOUR;
+
+/// A line of the history file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    recipient: String,
+    recipient_type: RecipientType,
+    #[serde(flatten)]
+    record: DispenseRecord,
+}
+
+/// A message to the thread writing the history file.
+enum Message {
+    Line(String),
+    /// Acknowledged once the lines sent before are written
+    Flush(mpsc::Sender<()>),
+}
+
+#[derive(Debug)]
+struct Writer {
+    path: PathBuf,
+    sender: mpsc::Sender<Message>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct Records {
+    /// How many of the oldest records were dropped, unknown if the recipient was evicted before
+    trimmed: Option<usize>,
+    /// The latest records, oldest first
+    kept: VecDeque<DispenseRecord>,
+    /// The key of the recipient in `State.recency`
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The records of the recipients dispensed to most recently
+    records: HashMap<Recipient, Records>,
+    /// The recipients in `records`, least recently dispensed to first
+    recency: BTreeMap<u64, Recipient>,
+    uses: u64,
+    /// Whether each recipient ever dispensed to was sent a committed dispense, by the hash of the
+    /// recipient, which is all that's kept of the evicted ones
+    known: HashMap<u64, bool>,
+    hasher: RandomState,
+    /// The number of recipients sent a committed dispense
+    recipients: u64,
+    /// The number and total amount of the committed dispenses
+    committed: (u64, u64),
+    /// The number and total amount of the recent committed dispenses, by hour
+    hourly: BTreeMap<u64, (u64, u64)>,
+}
+
+impl State {
+    fn push(&mut self, recipient: Recipient, record: DispenseRecord) {
+        let committed = record.status == DispenseStatus::Committed;
+        let hash = self.hasher.hash_one(recipient);
+        let first = !self.known.contains_key(&hash);
+        let was_committed = self.known.entry(hash).or_insert(false);
+        if committed && !*was_committed {
+            *was_committed = true;
+            self.recipients += 1;
+        }
+        if committed {
+            self.committed.0 += 1;
+            self.committed.1 += record.amount;
+            let hour = self
+                .hourly
+                .entry(record.timestamp - record.timestamp % HOUR)
+                .or_default();
+            hour.0 += 1;
+            hour.1 += record.amount;
+            let cutoff = record.timestamp.saturating_sub(HOURLY_RETENTION);
+            while let Some(entry) = self.hourly.first_entry() {
+                if *entry.key() >= cutoff {
+                    break;
+                }
+                entry.remove();
+            }
+        }
+
+        self.uses += 1;
+        let records = self.records.entry(recipient).or_insert_with(|| Records {
+            // the older records of a recipient that was evicted are only in the file
+            trimmed: first.then_some(0),
+            kept: VecDeque::new(),
+            used: 0,
+        });
+        self.recency.remove(&records.used);
+        records.used = self.uses;
+        self.recency.insert(self.uses, recipient);
+        records.kept.push_back(record);
+        if records.kept.len() > MAX_RECORDS_PER_RECIPIENT {
+            records.kept.pop_front();
+            if let Some(trimmed) = &mut records.trimmed {
+                *trimmed += 1;
+            }
+        }
+
+        if self.records.len() > MAX_CACHED_RECIPIENTS {
+            if let Some((_, evicted)) = self.recency.pop_first() {
+                self.records.remove(&evicted);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DispenseHistory {
+    /// The thread appending lines to the history file, if there's one
+    writer: Option<Writer>,
+    state: Mutex<State>,
+}
+
+impl DispenseHistory {
+    /// Loads the history from `file`, or keeps it in memory only if there's none.
+    pub fn load(file: Option<PathBuf>) -> anyhow::Result<Self> {
+        let mut state = State::default();
+        let mut writer = None;
+        if let Some(path) = file {
+            match File::open(&path) {
+                Ok(file) => {
+                    for (number, line) in BufReader::new(file).lines().enumerate() {
+                        let line = line.with_context(|| format!("unable to read {path:?}"))?;
+                        // a line cut short by a crash shouldn't lose the rest of the history
+                        match parse(&line) {
+                            Some((recipient, record)) => state.push(recipient, record),
+                            None => warn!("Skipping invalid record at {path:?}:{}", number + 1),
+                        }
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("unable to read {path:?}")),
+            }
+            info!(
+                "Loaded the dispense history of {} recipients",
+                state.known.len()
+            );
+
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("unable to open {path:?}"))?;
+            writer = Some(spawn_writer(path, file)?);
+        }
+
+        Ok(Self {
+            writer,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Records the outcome of a dispense: committed, or failed with `code`.
+    pub fn record(
+        &self,
+        recipient: Recipient,
+        amount: u64,
+        tx_id: Option<String>,
+        code: Option<ErrorCode>,
+    ) {
+        let record = DispenseRecord {
+            tx_id,
+            amount,
+            timestamp: now(),
+            status: match code {
+                Some(_) => DispenseStatus::Failed,
+                None => DispenseStatus::Committed,
+            },
+            code,
+        };
+
+        if let Some(writer) = &self.writer {
+            let (id, recipient_type) = match recipient {
+                Recipient::Address(address) => (format!("{address:#x}"), RecipientType::Address),
+                Recipient::Contract(contract_id) => {
+                    (format!("{contract_id:#x}"), RecipientType::Contract)
+                }
+            };
+            let line = serde_json::to_string(&Entry {
+                recipient: id,
+                recipient_type,
+                record: record.clone(),
+            })
+            .expect("records are serializable");
+            if writer.sender.send(Message::Line(line)).is_err() {
+                error!("The dispense history writer stopped, the record of {recipient} is lost");
+            }
+        }
+        self.state.lock().unwrap().push(recipient, record);
+    }
+
+    /// The number of recipients sent a committed dispense, and the number and total amount of
+    /// the committed dispenses.
+    pub fn totals(&self) -> (u64, u64, u64) {
+        let state = self.state.lock().unwrap();
+        (state.recipients, state.committed.0, state.committed.1)
+    }
+
+    /// Calls `f` with the start of each hour of the last month, and the number and total amount
+    /// of the committed dispenses within it.
+    pub fn for_each_hour(&self, mut f: impl FnMut(u64, u64, u64)) {
+        for (start, (dispenses, amount)) in self.state.lock().unwrap().hourly.iter() {
+            f(*start, *dispenses, *amount);
+        }
+    }
+
+    /// Up to `limit` records of the recipient, newest first, older than `cursor`. Returns the
+    /// cursor of the next page as well, if there's one. Pages that aren't in memory are read
+    /// from the file, so this blocks.
+    pub fn page(
+        &self,
+        recipient: &Recipient,
+        cursor: Option<u64>,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<DispenseRecord>, Option<u64>)> {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        {
+            let state = self.state.lock().unwrap();
+            if !state.known.contains_key(&state.hasher.hash_one(recipient)) {
+                return Ok((vec![], None));
+            }
+            if let Some(records) = state.records.get(recipient) {
+                // without a file, whatever is in memory is all there is
+                if let Some(trimmed) = records.trimmed.or(self.writer.is_none().then_some(0)) {
+                    let (start, end) = bounds(trimmed + records.kept.len(), cursor, limit);
+                    if start >= trimmed || self.writer.is_none() {
+                        let start = start.max(trimmed);
+                        let end = end.max(start);
+                        let page = records
+                            .kept
+                            .range(start - trimmed..end - trimmed)
+                            .rev()
+                            .cloned()
+                            .collect();
+                        return Ok((page, (start > trimmed).then_some(start as u64)));
+                    }
+                }
+            } else if self.writer.is_none() {
+                return Ok((vec![], None));
+            }
+        }
+
+        let records = self.read(recipient)?;
+        let (start, end) = bounds(records.len(), cursor, limit);
+        let page = records[start..end].iter().rev().cloned().collect();
+        Ok((page, (start > 0).then_some(start as u64)))
+    }
+
+    /// All the records of the recipient in the file, oldest first.
+    fn read(&self, recipient: &Recipient) -> anyhow::Result<Vec<DispenseRecord>> {
+        let Some(writer) = &self.writer else {
+            return Ok(vec![]);
+        };
+        // the lines still queued are written first
+        let (flushed, done) = mpsc::channel();
+        if writer.sender.send(Message::Flush(flushed)).is_ok() {
+            let _ = done.recv();
+        }
+
+        let id = match recipient {
+            Recipient::Address(address) => format!("{address:#x}"),
+            Recipient::Contract(contract_id) => format!("{contract_id:#x}"),
+        };
+        let file = File::open(&writer.path)
+            .with_context(|| format!("unable to read {:?}", writer.path))?;
+        let mut records = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("unable to read {:?}", writer.path))?;
+            // only the lines mentioning the recipient are worth parsing
+            if !line.contains(&id) {
+                continue;
+            }
+            if let Some((parsed, record)) = parse(&line) {
+                if parsed == *recipient {
+                    records.push(record);
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl Drop for DispenseHistory {
+    fn drop(&mut self) {
+        // closing the channel lets the writer finish the lines still queued
+        if let Some(Writer { sender, thread, .. }) = self.writer.take() {
+            drop(sender);
+            if thread.join().is_err() {
+                error!("The dispense history writer panicked");
+            }
+        }
+    }
+}
+
+/// The indexes of the records of a page among the `total` records of a recipient, oldest
+/// first. The cursor is the index of the oldest record returned, which new records don't shift.
+fn bounds(total: usize, cursor: Option<u64>, limit: usize) -> (usize, usize) {
+    let end = cursor.map_or(total, |cursor| (cursor as usize).min(total));
+    (end.saturating_sub(limit), end)
+}
+
+fn parse(line: &str) -> Option<(Recipient, DispenseRecord)> {
+    let entry = serde_json::from_str::<Entry>(line).ok()?;
+    Recipient::parse(&entry.recipient, entry.recipient_type)
+        .map(|recipient| (recipient, entry.record))
+}
+
+/// Appends the lines sent through the channel to the file, off the async runtime.
+fn spawn_writer(path: PathBuf, mut file: File) -> anyhow::Result<Writer> {
+    let (sender, receiver) = mpsc::channel::<Message>();
+    let thread_path = path.clone();
+    let thread = thread::Builder::new()
+        .name("dispense-history".to_string())
+        .spawn(move || {
+            for write in receiver {
+                match write {
+                    Message::Line(mut line) => {
+                        // a single write per line, so that lines are never interleaved
+                        line.push('\n');
+                        if let Err(e) = file.write_all(line.as_bytes()) {
+                            error!("Failed to write the dispense record to {thread_path:?}: {e}");
+                        }
+                    }
+                    Message::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        })
+        .context("unable to start the dispense history writer")?;
+    Ok(Writer {
+        path,
+        sender,
+        thread,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -0,0 +1,153 @@
+//! Translations of the pages and error messages. The locale is picked from the `lang` query
+//! parameter, then the `Accept-Language` header, and falls back to English. Each catalog holds
+//! the strings of the pages under `page` and the error messages by code under `errors`; the
+//! catalogs in the `locales` folder of the template directory override or add to the embedded
+//! ones. Page strings are HTML, and can use the template variables as `{name}`.
+
+use crate::models::ErrorCode;
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, fs, path::Path};
+use tracing::info;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+const EMBEDDED: [(&str, &str); 6] = [
+    ("en", include_str!("../locales/en.json")),
+    ("es", include_str!("../locales/es.json")),
+    ("fr", include_str!("../locales/fr.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("pt", include_str!("../locales/pt.json")),
+    ("zh", include_str!("../locales/zh.json")),
+];
+
+#[derive(Debug, Default, Deserialize)]
+struct Catalog {
+    #[serde(default)]
+    page: BTreeMap<String, String>,
+    /// Messages by error code. English has none, and keeps the messages of the error codes.
+    #[serde(default)]
+    errors: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct Catalogs {
+    catalogs: BTreeMap<String, Catalog>,
+}
+
+impl Catalogs {
+    pub fn load(template_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let mut catalogs = BTreeMap::new();
+        for (locale, embedded) in EMBEDDED {
+            let catalog = serde_json::from_str(embedded)
+                .with_context(|| format!("invalid embedded catalog {locale}"))?;
+            catalogs.insert(locale.to_string(), catalog);
+        }
+
+        let dir = template_dir
+            .map(|dir| dir.join("locales"))
+            .filter(|dir| dir.is_dir());
+        if let Some(dir) = dir {
+            for entry in fs::read_dir(&dir).with_context(|| format!("unable to read {dir:?}"))? {
+                let path = entry?.path();
+                let Some(locale) = path
+                    .extension()
+                    .filter(|extension| *extension == "json")
+                    .and(path.file_stem())
+                    .and_then(|stem| stem.to_str())
+                else {
+                    continue;
+                };
+                info!("Using the catalog {path:?}");
+                let contents = fs::read_to_string(&path)
+                    .with_context(|| format!("unable to read {path:?}"))?;
+                let catalog: Catalog = serde_json::from_str(&contents)
+                    .with_context(|| format!("invalid catalog {path:?}"))?;
+                let existing: &mut Catalog = catalogs.entry(locale.to_lowercase()).or_default();
+                existing.page.extend(catalog.page);
+                existing.errors.extend(catalog.errors);
+            }
+        }
+
+        // strings missing from a catalog are shown in English
+        let english = catalogs[DEFAULT_LOCALE].page.clone();
+        for catalog in catalogs.values_mut() {
+            for (key, value) in &english {
+                catalog
+                    .page
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        Ok(Self { catalogs })
+    }
+
+    /// The supported locale that best matches the `lang` parameter, or else the
+    /// `Accept-Language` header.
+    pub fn negotiate(&self, lang: Option<&str>, accept_language: Option<&str>) -> &str {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let tag = params.next()?.trim();
+                let quality = match params.find_map(|param| param.trim().strip_prefix("q=")) {
+                    Some(quality) => quality.trim().parse().ok()?,
+                    None => 1.0,
+                };
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // stable, so that ranges of the same quality keep their order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        lang.into_iter()
+            .chain(ranges.into_iter().map(|(tag, _)| tag))
+            .find_map(|tag| self.supported(tag))
+            .unwrap_or(DEFAULT_LOCALE)
+    }
+
+    /// The locale of the tag, or of its primary subtag, e.g. `pt` for `pt-BR`.
+    fn supported(&self, tag: &str) -> Option<&str> {
+        let tag = tag.to_lowercase();
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        let locale = [tag.as_str(), primary]
+            .into_iter()
+            .find_map(|candidate| self.catalogs.get_key_value(candidate))
+            .map(|(locale, _)| locale.as_str());
+        locale
+    }
+
+    /// The page strings of the locale, with the template variables substituted.
+    pub fn page(&self, locale: &str, vars: &Map<String, Value>) -> Value {
+        let catalog = self.catalog(locale);
+        let strings = catalog
+            .page
+            .iter()
+            .map(|(key, string)| {
+                let string = vars.iter().fold(string.clone(), |string, (name, value)| {
+                    let value = value.as_str().unwrap_or_default();
+                    string.replace(&format!("{{{name}}}"), &handlebars::html_escape(value))
+                });
+                (key.clone(), Value::String(string))
+            })
+            .collect();
+        Value::Object(strings)
+    }
+
+    /// The message of the error in the locale, if it's translated.
+    pub fn error_message(&self, locale: &str, code: ErrorCode) -> Option<&str> {
+        let code = serde_json::to_value(code).ok()?;
+        self.catalog(locale)
+            .errors
+            .get(code.as_str()?)
+            .map(String::as_str)
+    }
+
+    fn catalog(&self, locale: &str) -> &Catalog {
+        self.catalogs
+            .get(locale)
+            .unwrap_or(&self.catalogs[DEFAULT_LOCALE])
+    }
+}
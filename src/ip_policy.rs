@@ -0,0 +1,237 @@
+//! Policies for the networks requests come from, e.g. to block hosting providers or Tor exits.
+//!
+//! The policy file has one rule per line, for either a network range or an autonomous system:
+//!
+//! ```text
+//! # comments and empty lines are ignored
+//! 203.0.113.0/24  block
+//! 2001:db8::/32   captcha
+//! AS64496         reduce 100000
+//! 198.51.100.7    allow
+//! ```
+//!
+//! The most specific network range wins, and autonomous systems only apply to addresses that
+//! no range matches. Autonomous systems are looked up in the ASN databases, which are CSV files
+//! in the format of the GeoLite2 ASN database: `network,autonomous_system_number,...`.
+
+use anyhow::{anyhow, Context};
+use ipnet::IpNet;
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpPolicy {
+    /// Exempt from the rules of broader ranges
+    Allow,
+    Block,
+    /// The captcha has to be solved even by signed in users
+    Captcha,
+    /// At most this amount is dispensed
+    Reduce(u64),
+}
+
+impl FromStr for IpPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let policy = match (words.next(), words.next()) {
+            (Some("allow"), None) => IpPolicy::Allow,
+            (Some("block"), None) => IpPolicy::Block,
+            (Some("captcha"), None) => IpPolicy::Captcha,
+            (Some("reduce"), Some(amount)) => IpPolicy::Reduce(amount.parse()?),
+            _ => return Err(anyhow!("unknown policy `{s}`")),
+        };
+        match words.next() {
+            Some(extra) => Err(anyhow!("unexpected `{extra}` after the policy")),
+            None => Ok(policy),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Rules {
+    networks: Vec<(IpNet, IpPolicy)>,
+    asns: HashMap<u32, IpPolicy>,
+    /// Non-overlapping address ranges and their autonomous system, sorted by address
+    asn_ranges: Vec<(u128, u128, u32)>,
+}
+
+impl Rules {
+    fn load(
+        policy_file: &Path,
+        asn_databases: &[PathBuf],
+        captcha_enabled: bool,
+    ) -> anyhow::Result<Self> {
+        let mut rules = Rules::default();
+        let policies = fs::read_to_string(policy_file)
+            .with_context(|| format!("unable to read {policy_file:?}"))?;
+        for (number, line) in policies.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((target, policy)) = line.split_once(char::is_whitespace) else {
+                if line.is_empty() {
+                    continue;
+                }
+                return Err(anyhow!("{policy_file:?}:{}: missing policy", number + 1));
+            };
+            let policy = policy
+                .parse()
+                .with_context(|| format!("{policy_file:?}:{}", number + 1))?;
+            if policy == IpPolicy::Captcha && !captcha_enabled {
+                return Err(anyhow!(
+                    "{policy_file:?}:{}: the captcha policy requires CAPTCHA_SECRET",
+                    number + 1
+                ));
+            }
+            if let Some(asn) = target.strip_prefix("AS") {
+                let asn = asn
+                    .parse()
+                    .with_context(|| format!("{policy_file:?}:{}: invalid ASN", number + 1))?;
+                rules.asns.insert(asn, policy);
+            } else {
+                let network = IpNet::from_str(target)
+                    .or_else(|_| IpAddr::from_str(target).map(IpNet::from))
+                    .with_context(|| format!("{policy_file:?}:{}: invalid network", number + 1))?;
+                rules.networks.push((network, policy));
+            }
+        }
+
+        for database in asn_databases {
+            let ranges = fs::read_to_string(database)
+                .with_context(|| format!("unable to read {database:?}"))?;
+            for line in ranges.lines() {
+                let mut fields = line.splitn(3, ',');
+                let (Some(network), Some(asn)) = (fields.next(), fields.next()) else {
+                    continue;
+                };
+                // skips the header, along with anything else that isn't a range
+                let (Ok(network), Ok(asn)) = (IpNet::from_str(network), asn.parse()) else {
+                    continue;
+                };
+                rules
+                    .asn_ranges
+                    .push((key(network.network()), key(network.broadcast()), asn));
+            }
+        }
+        rules.asn_ranges.sort_unstable();
+
+        Ok(rules)
+    }
+
+    fn policy(&self, ip: IpAddr) -> Option<IpPolicy> {
+        let ip = ip.to_canonical();
+        if let Some((_, policy)) = self
+            .networks
+            .iter()
+            .filter(|(network, _)| network.contains(&ip))
+            .max_by_key(|(network, _)| network.prefix_len())
+        {
+            return Some(*policy);
+        }
+
+        let key = key(ip);
+        let index = self
+            .asn_ranges
+            .partition_point(|(start, _, _)| *start <= key);
+        let (_, end, asn) = self.asn_ranges.get(index.checked_sub(1)?)?;
+        if key > *end {
+            return None;
+        }
+        self.asns.get(asn).copied()
+    }
+}
+
+/// Orders IPv4 and IPv6 addresses in a single space.
+fn key(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IpPolicies {
+    policy_file: Option<PathBuf>,
+    asn_databases: Vec<PathBuf>,
+    /// Whether the faucet verifies captchas, without which the captcha policy can't apply
+    captcha_enabled: bool,
+    rules: RwLock<Rules>,
+    /// When the files were last read, successfully or not
+    read_at: RwLock<Option<SystemTime>>,
+}
+
+impl IpPolicies {
+    pub fn load(
+        policy_file: Option<PathBuf>,
+        asn_databases: Vec<PathBuf>,
+        captcha_enabled: bool,
+    ) -> anyhow::Result<Self> {
+        let policies = Self {
+            policy_file,
+            asn_databases,
+            captcha_enabled,
+            ..Default::default()
+        };
+        policies.reload()?;
+        Ok(policies)
+    }
+
+    /// Reloads the rules from the policy file and the ASN databases. The current rules are kept
+    /// if they can't be loaded.
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let Some(policy_file) = &self.policy_file else {
+            return Ok(());
+        };
+        *self.read_at.write().unwrap() = Some(SystemTime::now());
+        let rules = Rules::load(policy_file, &self.asn_databases, self.captcha_enabled)?;
+        info!(
+            "Loaded {} network and {} ASN policies",
+            rules.networks.len(),
+            rules.asns.len()
+        );
+        *self.rules.write().unwrap() = rules;
+        Ok(())
+    }
+
+    pub fn policy(&self, ip: IpAddr) -> Option<IpPolicy> {
+        self.rules.read().unwrap().policy(ip)
+    }
+
+    /// Whether any of the files changed since they were last read.
+    fn modified(&self) -> bool {
+        let Some(read_at) = *self.read_at.read().unwrap() else {
+            return self.policy_file.is_some();
+        };
+        self.policy_file
+            .iter()
+            .chain(&self.asn_databases)
+            .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .any(|modified| modified >= read_at)
+    }
+}
+
+/// Reloads the policies whenever their files change, checking every `interval` seconds.
+pub async fn watch(policies: Arc<IpPolicies>, interval: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        interval.tick().await;
+        // the ASN databases can be large, so they're read off the async runtime
+        let policies = policies.clone();
+        let reloaded =
+            tokio::task::spawn_blocking(move || policies.modified().then(|| policies.reload()))
+                .await;
+        match reloaded {
+            Ok(Some(Err(e))) => warn!("Failed to reload the IP policies: {e:#}"),
+            Err(e) => warn!("Failed to reload the IP policies: {e}"),
+            Ok(_) => {}
+        }
+    }
+}
@@ -1,45 +1,86 @@
 use crate::{
+    adaptive::AdaptiveLimiter,
     config::Config,
-    constants::{MAX_CONCURRENT_REQUESTS, WALLET_SECRET_DEV_KEY},
+    constants::{EVENT_CHANNEL_CAPACITY, MAX_CONCURRENT_REQUESTS, WALLET_SECRET_DEV_KEY},
     dispense_tracker::DispenseTracker,
+    history::DispenseHistory,
+    i18n::Catalogs,
+    ip_policy::IpPolicies,
+    models::{DispenseError, ErrorCode, FaucetEvent, RecipientType},
+    oauth::OAuthSessions,
+    ownership::OwnershipChallenges,
     routes::health,
+    shutdown::InFlightDispenses,
+    signer::{FaucetSigner, RemoteSigner},
+    stats::Stats,
+    sybil::SybilDetector,
+    templates::Templates,
+    wallet::FaucetWallet,
+    wallet_pool::WalletPool,
+    webhooks::Webhooks,
 };
 use anyhow::anyhow;
 use axum::{
     error_handling::HandleErrorLayer,
-    http::{header::CACHE_CONTROL, HeaderValue, StatusCode},
-    response::IntoResponse,
-    routing::{get, post},
-    BoxError, Extension, Json, Router,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_SECURITY_POLICY, VARY},
+        HeaderValue,
+    },
+    middleware,
+    routing::{delete, get, post},
+    BoxError, Extension, Router,
 };
 use fuel_core_client::client::FuelClient;
 use fuel_tx::UtxoId;
-use fuel_types::Address;
-use fuels_accounts::{provider::Provider, wallet::WalletUnlocked, ViewOnlyAccount};
-use fuels_core::types::node_info::NodeInfo;
+use fuel_types::{Address, ContractId};
+use fuels_accounts::{provider::Provider, wallet::WalletUnlocked};
+use fuels_core::types::{
+    bech32::{Bech32Address, Bech32ContractId},
+    node_info::NodeInfo,
+};
 use secrecy::{ExposeSecret, Secret};
-use serde_json::json;
 use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
     net::{SocketAddr, TcpListener},
+    str::FromStr,
     sync::{Arc, Mutex},
     time::Duration,
 };
-use tokio::task::JoinHandle;
+use tokio::{
+    sync::{broadcast, oneshot},
+    task::JoinHandle,
+};
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
     set_header::SetResponseHeaderLayer,
     trace::TraceLayer,
 };
-use tracing::info;
+use tracing::{error, info, warn};
 
 pub mod config;
 pub mod models;
+pub mod openapi;
+pub mod signer;
+pub mod wallet;
 
+mod adaptive;
 mod constants;
 mod dispense_tracker;
+mod history;
+mod i18n;
+mod ip_policy;
+mod oauth;
+mod ownership;
 mod recaptcha;
 mod routes;
+mod shutdown;
+mod stats;
+mod sybil;
+mod templates;
+mod wallet_pool;
+mod webhooks;
 
 pub use dispense_tracker::{Clock, StdTime};
 
@@ -77,14 +118,160 @@ impl FaucetState {
     }
 }
 
-pub type SharedFaucetState = Arc<tokio::sync::Mutex<FaucetState>>;
-pub type SharedWallet = Arc<WalletUnlocked>;
+pub type SharedWalletPool = Arc<WalletPool>;
 pub type SharedConfig = Arc<Config>;
 pub type SharedDispenseTracker = Arc<Mutex<DispenseTracker>>;
+pub type SharedInFlightDispenses = Arc<InFlightDispenses>;
+pub type SharedOAuthSessions = Arc<OAuthSessions>;
+pub type SharedOwnershipChallenges = Arc<OwnershipChallenges>;
+pub type SharedSybilDetector = Arc<SybilDetector>;
+pub type SharedAdaptiveLimiter = Arc<AdaptiveLimiter>;
+pub type SharedIpPolicies = Arc<IpPolicies>;
+pub type SharedDispenseHistory = Arc<DispenseHistory>;
+pub type SharedStats = Arc<Stats>;
+pub type SharedTemplates = Arc<Templates>;
+pub type SharedCatalogs = Arc<Catalogs>;
+pub type EventSender = broadcast::Sender<FaucetEvent>;
+
+/// Connects to the node and sets up the faucet wallet, signing either with the configured
+/// remote signer, the secret key, or the keystore, in that order of preference.
+pub async fn setup_wallet(config: &Config) -> FaucetWallet {
+    let provider = Provider::connect(config.node_url.clone())
+        .await
+        .expect("Should create a provider");
+
+    let signer = match &config.remote_signer_url {
+        Some(url) => {
+            let address = config
+                .remote_signer_address
+                .as_deref()
+                .and_then(parse_address)
+                .expect("`REMOTE_SIGNER_ADDRESS` must be set to a valid address");
+            let signer = RemoteSigner::new(
+                url,
+                address,
+                config.remote_signer_token.clone(),
+                Duration::from_secs(config.timeout),
+            )
+            .expect("Invalid `REMOTE_SIGNER_URL`");
+            FaucetSigner::Remote(signer)
+        }
+        None => FaucetSigner::Local(Box::new(load_local_wallet(config))),
+    };
+
+    FaucetWallet::new(signer, Some(provider))
+}
+
+/// Sets up the main faucet wallet followed by the additional wallets configured with
+/// `WALLET_SECRET_KEYS`.
+pub async fn setup_wallets(config: &Config) -> Vec<FaucetWallet> {
+    let wallet = setup_wallet(config).await;
+    let provider = wallet.provider().cloned();
+
+    let additional_wallets = config.wallet_secret_keys.iter().map(|secret| {
+        let wallet = WalletUnlocked::new_from_private_key(
+            secret
+                .expose_secret()
+                .parse()
+                .expect("Unable to load secret key from `WALLET_SECRET_KEYS`"),
+            None,
+        );
+        FaucetWallet::new(FaucetSigner::Local(Box::new(wallet)), provider.clone())
+    });
+
+    std::iter::once(wallet).chain(additional_wallets).collect()
+}
+
+fn load_local_wallet(config: &Config) -> WalletUnlocked {
+    let secret = match (&config.wallet_secret_key, &config.wallet_keystore) {
+        (Some(secret), _) => secret.clone(),
+        (None, Some(keystore)) => {
+            let password = config
+                .wallet_keystore_password
+                .as_ref()
+                .expect("A password is required to decrypt the wallet keystore");
+            return WalletUnlocked::load_keystore(keystore, password.expose_secret(), None)
+                .expect("Unable to decrypt the wallet keystore");
+        }
+        (None, None) if config.use_dev_wallet => {
+            warn!("No wallet configured, using the public development wallet");
+            Secret::new(WALLET_SECRET_DEV_KEY.to_string())
+        }
+        (None, None) => panic!(
+            "No wallet configured, set `WALLET_SECRET_KEY`, `WALLET_SECRET_KEY_FILE`, \
+            `WALLET_KEYSTORE` or `REMOTE_SIGNER_URL`, or `USE_DEV_WALLET=true` for local development"
+        ),
+    };
+    WalletUnlocked::new_from_private_key(
+        secret
+            .expose_secret()
+            .parse()
+            .expect("Unable to load secret key"),
+        None,
+    )
+}
+
+/// Parses an address given either in hex or bech32.
+pub fn parse_address(address: &str) -> Option<Address> {
+    if let Ok(address) = Address::from_str(address) {
+        Some(address)
+    } else if let Ok(address) = Bech32Address::from_str(address) {
+        Some(address.into())
+    } else {
+        None
+    }
+}
+
+/// Parses a contract id given either in hex or bech32.
+pub fn parse_contract_id(contract_id: &str) -> Option<ContractId> {
+    if let Ok(contract_id) = ContractId::from_str(contract_id) {
+        Some(contract_id)
+    } else if let Ok(contract_id) = Bech32ContractId::from_str(contract_id) {
+        Some(contract_id.into())
+    } else {
+        None
+    }
+}
+
+/// Who funds are dispensed to. Addresses and contracts are rate limited separately.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Recipient {
+    Address(Address),
+    Contract(ContractId),
+}
+
+impl Recipient {
+    pub fn parse(recipient: &str, recipient_type: RecipientType) -> Option<Self> {
+        match recipient_type {
+            RecipientType::Address => parse_address(recipient).map(Recipient::Address),
+            RecipientType::Contract => parse_contract_id(recipient).map(Recipient::Contract),
+        }
+    }
+}
+
+impl Display for Recipient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Recipient::Address(address) => write!(f, "{address:#x}"),
+            Recipient::Contract(contract_id) => write!(f, "contract {contract_id:#x}"),
+        }
+    }
+}
 
 pub async fn start_server(
     service_config: Config,
     clock: impl Clock + 'static,
+) -> (SocketAddr, JoinHandle<Result<(), anyhow::Error>>) {
+    start_server_with_shutdown(service_config, clock, std::future::pending()).await
+}
+
+/// Starts the server, which shuts down gracefully once `shutdown` resolves: new dispenses are
+/// rejected and outstanding ones are given up to `shutdown_timeout` seconds to be committed, and
+/// their webhooks to be delivered. Webhooks still retrying by then are dead-lettered.
+pub async fn start_server_with_shutdown(
+    service_config: Config,
+    clock: impl Clock + 'static,
+    shutdown: impl Future<Output = ()> + Send + 'static,
 ) -> (SocketAddr, JoinHandle<Result<(), anyhow::Error>>) {
     info!("{:#?}", &service_config);
 
@@ -93,60 +280,194 @@ pub async fn start_server(
         .expect("unable to connect to the fuel node api");
 
     let node_info = client.node_info().await.expect("Unable to fetch node info");
-    let provider = Provider::connect(service_config.node_url.clone())
-        .await
-        .expect("Should create a provider");
-    let base_asset_id = *provider.consensus_parameters().base_asset_id();
 
-    // setup wallet
-    let secret = service_config
-        .wallet_secret_key
-        .clone()
-        .unwrap_or_else(|| Secret::new(WALLET_SECRET_DEV_KEY.to_string()));
-    let wallet = WalletUnlocked::new_from_private_key(
-        secret
-            .expose_secret()
-            .parse()
-            .expect("Unable to load secret key"),
-        Some(provider),
+    // setup wallets
+    let wallets = setup_wallets(&service_config).await;
+    for wallet in &wallets {
+        let balance = wallet::spendable_coins(wallet)
+            .await
+            .expect("Failed to fetch initial balance from fuel core")
+            .iter()
+            .map(|coin| coin.amount)
+            .sum::<u64>();
+        info!("Faucet Account: {:#x}", Address::from(wallet.address()));
+        info!("Faucet Balance: {}", balance);
+    }
+    let wallet_pool = WalletPool::new(wallets, &node_info.clone().into());
+    // each wallet chains its own transactions, up to the max depth of the txpool
+    let max_concurrent_dispenses = node_info.max_depth as usize * wallet_pool.len();
+
+    let in_flight = Arc::new(InFlightDispenses::default());
+
+    // the rate limits share the clock, so that they agree on the time
+    let clock = Arc::new(clock);
+    let adaptive_limiter = Arc::new(AdaptiveLimiter::new(&service_config, clock.clone()));
+    let stats = Arc::new(Stats::new(
+        service_config.stats_cache_duration,
+        clock.clone(),
+    ));
+
+    let sybil_detector = Arc::new(SybilDetector::default());
+    let sybil_analyzer = service_config.sybil_detection.then(|| {
+        // funds returned to the faucet don't make it a collector
+        let ignored = wallet_pool
+            .wallets()
+            .map(|pooled| Address::from(pooled.wallet.address()))
+            .chain(
+                service_config
+                    .sybil_ignored_addresses
+                    .iter()
+                    .map(|address| {
+                        parse_address(address)
+                            .expect("expected valid addresses for SYBIL_IGNORED_ADDRESSES")
+                    }),
+            )
+            .collect();
+        tokio::spawn(sybil::run(
+            sybil_detector.clone(),
+            client.clone(),
+            ignored,
+            service_config.sybil_min_cluster_size,
+            service_config.sybil_tracking_period,
+            service_config.sybil_denylist_duration,
+            service_config.sybil_analysis_interval,
+        ))
+    });
+
+    let ip_policies = Arc::new(
+        IpPolicies::load(
+            service_config.ip_policy_file.clone(),
+            service_config.ip_asn_databases.clone(),
+            service_config.captcha_secret.is_some(),
+        )
+        .expect("Unable to load the IP policies"),
     );
+    let ip_policy_watcher = service_config.ip_policy_file.is_some().then(|| {
+        tokio::spawn(ip_policy::watch(
+            ip_policies.clone(),
+            service_config.ip_policy_reload_interval,
+        ))
+    });
 
-    let balance = wallet
-        .get_coins(base_asset_id)
-        .await
-        .expect("Failed to fetch initial balance from fuel core")
-        .into_iter()
-        .filter_map(|coin| match coin.status {
-            fuels_core::types::coin::CoinStatus::Unspent => Some(coin.amount),
-            _ => None,
-        })
-        .sum::<u64>();
-    info!("Faucet Account: {:#x}", Address::from(wallet.address()));
-    info!("Faucet Balance: {}", balance);
+    let templates = Arc::new(
+        Templates::load(
+            service_config.template_dir.as_deref(),
+            &service_config.template_vars,
+        )
+        .expect("Unable to load the templates"),
+    );
+    let catalogs = Arc::new(
+        Catalogs::load(service_config.template_dir.as_deref())
+            .expect("Unable to load the translation catalogs"),
+    );
+    let history = Arc::new(
+        DispenseHistory::load(service_config.dispense_history_file.clone())
+            .expect("Unable to load the dispense history"),
+    );
+
+    let (events, _) = broadcast::channel::<FaucetEvent>(EVENT_CHANNEL_CAPACITY);
+    let webhooks = Webhooks::new(&service_config)
+        .expect("Invalid webhook configuration")
+        .map(Arc::new);
+    let mut webhook_notifier = webhooks
+        .clone()
+        .map(|webhooks| tokio::spawn(webhooks::run(webhooks, events.subscribe())));
 
     // setup routes
-    let app = Router::new()
-        .route(
-            "/",
-            get(routes::main).layer(SetResponseHeaderLayer::<_>::overriding(
-                CACHE_CONTROL,
-                HeaderValue::from_static("public, max-age=3600, immutable"),
-            )),
-        )
+    let api = Router::new()
+        .route("/openapi.json", get(routes::openapi))
         .route("/health", get(health))
+        .route("/metrics", get(routes::metrics))
+        .route("/events", get(routes::events))
+        .route("/stats", get(routes::stats))
         .route("/dispense", get(routes::dispense_info))
+        .route(
+            "/dispense/eligibility/:address",
+            get(routes::dispense_eligibility),
+        )
+        .route("/dispense/history/:address", get(routes::dispense_history))
+        .route(
+            "/dispense/nonce/:address",
+            // nonces are kept in memory, so issuing them is limited like dispensing
+            get(routes::dispense_nonce).route_layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(handle_error))
+                    .buffer(MAX_CONCURRENT_REQUESTS)
+                    .concurrency_limit(max_concurrent_dispenses)
+                    .into_inner(),
+            ),
+        )
         .route(
             "/dispense",
             post(routes::dispense_tokens).route_layer(
                 // Apply rate limiting specifically on the dispense endpoint, and
                 // only allow a single instance at a time to avoid race conditions
                 ServiceBuilder::new()
+                    .layer(middleware::from_fn(routes::track_dispense_outcome))
                     .layer(HandleErrorLayer::new(handle_error))
                     .buffer(MAX_CONCURRENT_REQUESTS)
-                    .concurrency_limit(node_info.max_depth as usize)
+                    .concurrency_limit(max_concurrent_dispenses)
                     .into_inner(),
             ),
+        );
+
+    let api = if service_config.sybil_detection {
+        api.route("/admin/sybil", get(routes::sybil_report))
+            .route("/admin/sybil/:address", delete(routes::sybil_unlist))
+    } else {
+        api
+    };
+
+    // sign in is only offered if an OAuth app is configured
+    let api = if service_config.github_client_id.is_some() {
+        api.route("/auth/github", get(routes::github_login))
+            .route("/auth/github/callback", get(routes::github_callback))
+    } else {
+        api
+    };
+
+    // only the faucet itself and the allowed origins can frame the widget
+    let frame_ancestors = std::iter::once("'self'")
+        .chain(
+            service_config
+                .embed_allowed_origins
+                .iter()
+                .map(String::as_str),
         )
+        .collect::<Vec<_>>()
+        .join(" ");
+    let embed_policy = HeaderValue::from_str(&format!("frame-ancestors {frame_ancestors}"))
+        .expect("Invalid embed allowed origins");
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(routes::main)
+                .layer(SetResponseHeaderLayer::<_>::overriding(
+                    CACHE_CONTROL,
+                    HeaderValue::from_static("public, max-age=3600, immutable"),
+                ))
+                // the page is rendered in the locale the browser asks for
+                .layer(SetResponseHeaderLayer::<_>::overriding(
+                    VARY,
+                    HeaderValue::from_static("accept-language"),
+                )),
+        )
+        .route(
+            "/embed",
+            get(routes::embed)
+                .layer(SetResponseHeaderLayer::<_>::overriding(
+                    CONTENT_SECURITY_POLICY,
+                    embed_policy,
+                ))
+                .layer(SetResponseHeaderLayer::<_>::overriding(
+                    VARY,
+                    HeaderValue::from_static("accept-language"),
+                )),
+        )
+        .nest("/v1", api.clone())
+        // Keep serving the API at the root so that existing integrations continue to work
+        .merge(api)
         .layer(
             ServiceBuilder::new()
                 // Handle errors from middleware
@@ -155,14 +476,23 @@ pub async fn start_server(
                 .concurrency_limit(MAX_CONCURRENT_REQUESTS)
                 .timeout(Duration::from_secs(60))
                 .layer(TraceLayer::new_for_http())
-                .layer(Extension(Arc::new(wallet)))
+                .layer(Extension(Arc::new(wallet_pool)))
                 .layer(Extension(Arc::new(client)))
                 .layer(Extension(Arc::new(node_info.clone())))
-                .layer(Extension(Arc::new(tokio::sync::Mutex::new(
-                    FaucetState::new(&node_info.into()),
-                ))))
                 .layer(Extension(Arc::new(service_config.clone())))
                 .layer(Extension(Arc::new(Mutex::new(DispenseTracker::new(clock)))))
+                .layer(Extension(in_flight.clone()))
+                .layer(Extension(Arc::new(OAuthSessions::default())))
+                .layer(Extension(Arc::new(OwnershipChallenges::default())))
+                .layer(Extension(sybil_detector))
+                .layer(Extension(adaptive_limiter))
+                .layer(Extension(ip_policies))
+                .layer(Extension(events))
+                .layer(Extension(history))
+                .layer(Extension(stats))
+                .layer(Extension(templates))
+                .layer(Extension(catalogs))
+                .layer(middleware::from_fn(routes::localize_errors))
                 .layer(
                     CorsLayer::new()
                         .allow_origin(Any)
@@ -177,41 +507,79 @@ pub async fn start_server(
     let listener = TcpListener::bind(addr).unwrap();
     let bound_addr = listener.local_addr().unwrap();
     info!("listening on {}", bound_addr);
+    let shutdown_timeout = Duration::from_secs(service_config.shutdown_timeout);
     (
         bound_addr,
         tokio::spawn(async move {
-            axum::Server::from_tcp(listener)
-                .unwrap()
-                .serve(app.into_make_service())
-                .await
-                .map_err(|e| anyhow!(e))
+            let (draining_tx, draining_rx) = oneshot::channel();
+            let signal = {
+                let in_flight = in_flight.clone();
+                async move {
+                    shutdown.await;
+                    info!("shutting down, waiting for in-flight dispenses to complete");
+                    in_flight.begin_shutdown();
+                    let _ = draining_tx.send(());
+                }
+            };
+
+            let notifier = webhook_notifier.as_mut().zip(webhooks.clone());
+            let server = async move {
+                axum::Server::from_tcp(listener)
+                    .unwrap()
+                    .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                    .with_graceful_shutdown(signal)
+                    .await
+                    .map_err(|e| anyhow!(e))?;
+                // dispenses aren't tied to their requests, so some may still be running
+                in_flight.wait_idle().await;
+                // the notifier stops once the dispenses dropped the event channel, and the
+                // webhooks of their events are delivered as well
+                if let Some((notifier, webhooks)) = notifier {
+                    let _ = notifier.await;
+                    webhooks.flush().await;
+                }
+                Ok(())
+            };
+
+            let deadline = async move {
+                if draining_rx.await.is_err() {
+                    // the server stopped without ever being asked to shut down
+                    std::future::pending::<()>().await;
+                }
+                tokio::time::sleep(shutdown_timeout).await;
+            };
+
+            let result = tokio::select! {
+                result = server => result,
+                _ = deadline => {
+                    warn!("timed out waiting for in-flight dispenses and webhooks to complete");
+                    Ok(())
+                }
+            };
+            for task in [sybil_analyzer, ip_policy_watcher, webhook_notifier]
+                .into_iter()
+                .flatten()
+            {
+                task.abort();
+            }
+            // the deliveries still retrying go to the dead-letter file rather than being lost
+            if let Some(webhooks) = webhooks {
+                webhooks.abandon().await;
+            }
+            result
         }),
     )
 }
 
-async fn handle_error(error: BoxError) -> impl IntoResponse {
+async fn handle_error(error: BoxError) -> DispenseError {
     if error.is::<tower::timeout::error::Elapsed>() {
-        return (
-            StatusCode::REQUEST_TIMEOUT,
-            Json(json!({
-                "error": "request timed out"
-            })),
-        );
+        return ErrorCode::RequestTimeout.into();
     }
 
     if error.is::<tower::load_shed::error::Overloaded>() {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(json!({
-                "error": "service is overloaded, try again later"
-            })),
-        );
+        return ErrorCode::Overloaded.into();
     }
 
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(json!({
-            "error": format!("Unhandled internal error: {error}")
-        })),
-    )
+    error!("Unhandled internal error: {error}");
+    ErrorCode::InternalError.into()
 }
@@ -1,13 +1,133 @@
-use fuel_faucet::{config::Config, start_server, StdTime};
+use anyhow::anyhow;
+use clap::{Parser, Subcommand};
+use fuel_faucet::{
+    config::Config, parse_address, setup_wallet, start_server_with_shutdown, wallet, StdTime,
+};
+use fuel_types::Address;
+use tokio::signal;
 use tracing_subscriber::EnvFilter;
 
+/// A token faucet for onboarding fuel users. Runs the faucet server unless a subcommand is given.
+#[derive(Parser, Debug)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Maintenance of the faucet wallet. These submit transactions from the same wallet as the
+    /// server, so they are best run while the faucet is stopped.
+    #[command(subcommand)]
+    Wallet(WalletCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum WalletCommand {
+    /// Print the spendable balance of the wallet
+    Balance,
+    /// List the spendable coins of the wallet, largest first
+    Coins {
+        /// Only list this many coins
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Create `count` coins of `amount` each
+    Split {
+        #[arg(long)]
+        count: u16,
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Merge the smallest coins together until at most `target` coins remain
+    Consolidate {
+        #[arg(long, default_value_t = 1)]
+        target: usize,
+    },
+    /// Transfer the whole balance of the wallet to another address
+    Sweep {
+        /// The recipient, either hex or bech32 encoded
+        #[arg(long)]
+        to: String,
+    },
+}
+
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
     let config = Config::default();
     init_logger(&config);
-    let clock = StdTime {};
-    let (_, task) = start_server(config, clock).await;
-    let _ = task.await.unwrap();
+
+    match cli.command {
+        None => {
+            let clock = StdTime {};
+            let (_, task) = start_server_with_shutdown(config, clock, shutdown_signal()).await;
+            let _ = task.await.unwrap();
+        }
+        Some(Command::Wallet(command)) => run_wallet_command(&config, command).await?,
+    }
+
+    Ok(())
+}
+
+async fn run_wallet_command(config: &Config, command: WalletCommand) -> anyhow::Result<()> {
+    let wallet = setup_wallet(config).await;
+    println!("Faucet Account: {:#x}", Address::from(wallet.address()));
+
+    match command {
+        WalletCommand::Balance => {
+            let coins = wallet::spendable_coins(&wallet).await?;
+            let balance = coins.iter().map(|coin| coin.amount).sum::<u64>();
+            println!("Balance: {balance} in {} coins", coins.len());
+        }
+        WalletCommand::Coins { limit } => {
+            let coins = wallet::spendable_coins(&wallet).await?;
+            for coin in coins.iter().take(limit.unwrap_or(coins.len())) {
+                println!("{:#x} {}", coin.utxo_id, coin.amount);
+            }
+        }
+        WalletCommand::Split { count, amount } => {
+            let tx_id = wallet::split(&wallet, count, amount).await?;
+            println!("Split into {count} coins of {amount} in {tx_id:#x}");
+        }
+        WalletCommand::Consolidate { target } => {
+            for tx_id in wallet::consolidate(&wallet, target).await? {
+                println!("Consolidated coins in {tx_id:#x}");
+            }
+        }
+        WalletCommand::Sweep { to } => {
+            let recipient = parse_address(&to).ok_or_else(|| anyhow!("invalid address: {to}"))?;
+            for tx_id in wallet::sweep(&wallet, recipient).await? {
+                println!("Swept coins to {recipient:#x} in {tx_id:#x}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
 
 fn init_logger(config: &Config) {
@@ -1,33 +1,30 @@
 use std::fmt::{self, Display, Formatter};
 
+pub use fuel_faucet_client::models::{
+    DispenseHistoryResponse, DispenseInfoResponse, DispenseInput, DispenseRecord, DispenseResponse,
+    DispenseStatus, EligibilityResponse, ErrorCode, ErrorResponse, FaucetEvent, HealthResponse,
+    NonceResponse, OwnershipProof, RateLimitMode, RecipientType, StatsBucket, StatsResponse,
+    SybilCluster, SybilReport, WalletBalance, WebhookEvent, WebhookPayload,
+};
 use reqwest::StatusCode;
-use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DispenseInfoResponse {
-    pub amount: u64,
-    pub asset_id: String,
-}
-
-#[derive(Deserialize, Debug)]
-pub struct DispenseInput {
-    pub address: String,
-    pub captcha: String,
-}
-
-#[derive(Serialize, Debug)]
-pub struct DispenseResponse {
-    pub status: String,
-    pub tokens: u64,
-    pub tx_id: String,
-}
 
 #[derive(Debug)]
 pub struct DispenseError {
     pub status: StatusCode,
+    pub code: ErrorCode,
     pub error: String,
 }
 
+impl From<ErrorCode> for DispenseError {
+    fn from(code: ErrorCode) -> Self {
+        Self {
+            status: code.status(),
+            code,
+            error: code.message().to_string(),
+        }
+    }
+}
+
 impl Display for DispenseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{self:?}")
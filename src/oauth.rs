@@ -0,0 +1,194 @@
+//! Optional sign in with GitHub. Signed in accounts are rate limited by their account id rather
+//! than by recipient, and get a larger budget than anonymous users.
+
+use crate::config::Config;
+use anyhow::anyhow;
+use fuel_types::Bytes32;
+use rand::Rng;
+use reqwest::{header::USER_AGENT, Url};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The cookie holding the session of a signed in user.
+pub const SESSION_COOKIE: &str = "faucet_session";
+/// How long a session lasts, in seconds.
+pub const SESSION_DURATION: u64 = 24 * 60 * 60;
+/// How long a user has to complete the sign in with the provider, in seconds.
+const LOGIN_DURATION: u64 = 10 * 60;
+/// The most sign ins kept in progress at once, so that starting them anonymously can't exhaust
+/// the memory. The oldest ones make room for new ones.
+const MAX_PENDING_LOGINS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    /// The account id at the provider, which doesn't change when the account is renamed
+    pub id: u64,
+    pub login: String,
+    /// When the account was created, in seconds since the unix epoch
+    pub created_at: u64,
+}
+
+impl Identity {
+    pub fn account_age(&self) -> u64 {
+        now().saturating_sub(self.created_at)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserResponse {
+    id: u64,
+    login: String,
+    created_at: String,
+}
+
+/// Logins in progress and the sessions of signed in users, kept in memory.
+#[derive(Debug, Default)]
+pub struct OAuthSessions {
+    pending_logins: Mutex<HashMap<String, u64>>,
+    sessions: Mutex<HashMap<String, (Identity, u64)>>,
+}
+
+impl OAuthSessions {
+    /// Starts a login, returning the state that the provider has to send back to the callback.
+    pub fn start_login(&self) -> String {
+        let state = random_token();
+        let mut pending_logins = self.pending_logins.lock().unwrap();
+        let now = now();
+        pending_logins.retain(|_, expires_at| *expires_at > now);
+        if pending_logins.len() >= MAX_PENDING_LOGINS {
+            let oldest = pending_logins
+                .iter()
+                .min_by_key(|(_, expires_at)| **expires_at)
+                .map(|(state, _)| state.clone());
+            if let Some(oldest) = oldest {
+                pending_logins.remove(&oldest);
+            }
+        }
+        pending_logins.insert(state.clone(), now + LOGIN_DURATION);
+        state
+    }
+
+    /// Checks that `state` belongs to a login started by this faucet, which can only complete once.
+    pub fn finish_login(&self, state: &str) -> bool {
+        self.pending_logins
+            .lock()
+            .unwrap()
+            .remove(state)
+            .is_some_and(|expires_at| expires_at > now())
+    }
+
+    /// Creates a session for the identity, returning its token.
+    pub fn create_session(&self, identity: Identity) -> String {
+        let token = random_token();
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = now();
+        sessions.retain(|_, (_, expires_at)| *expires_at > now);
+        sessions.insert(token.clone(), (identity, now + SESSION_DURATION));
+        token
+    }
+
+    pub fn identity(&self, token: &str) -> Option<Identity> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(token)
+            .filter(|(_, expires_at)| *expires_at > now())
+            .map(|(identity, _)| identity.clone())
+    }
+}
+
+/// The url of the provider's authorization page that users are sent to in order to sign in.
+pub fn authorize_url(config: &Config, state: &str) -> anyhow::Result<Url> {
+    let client_id = config
+        .github_client_id
+        .as_ref()
+        .ok_or_else(|| anyhow!("GitHub sign in isn't configured"))?;
+    let mut url = Url::parse(&config.github_authorize_url)?;
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("state", state)
+        .append_pair("allow_signup", "false");
+    if let Some(redirect_url) = &config.oauth_redirect_url {
+        url.query_pairs_mut()
+            .append_pair("redirect_uri", redirect_url);
+    }
+    Ok(url)
+}
+
+/// Exchanges the code the provider sent to the callback for the identity of the user.
+pub async fn fetch_identity(config: &Config, code: &str) -> anyhow::Result<Identity> {
+    let (Some(client_id), Some(client_secret)) =
+        (&config.github_client_id, &config.github_client_secret)
+    else {
+        return Err(anyhow!("GitHub sign in isn't configured"));
+    };
+    let client = reqwest::Client::new();
+
+    let mut params = vec![
+        ("client_id", client_id.as_str()),
+        ("client_secret", client_secret.expose_secret().as_str()),
+        ("code", code),
+    ];
+    if let Some(redirect_url) = &config.oauth_redirect_url {
+        params.push(("redirect_uri", redirect_url));
+    }
+    let token = client
+        .post(&config.github_token_url)
+        .header("Accept", "application/json")
+        .form(&params)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TokenResponse>()
+        .await?;
+    let access_token = match token {
+        TokenResponse {
+            access_token: Some(access_token),
+            ..
+        } => access_token,
+        TokenResponse { error, .. } => {
+            return Err(anyhow!("no access token: {}", error.unwrap_or_default()))
+        }
+    };
+
+    let user = client
+        .get(&config.github_user_url)
+        .bearer_auth(access_token)
+        .header("Accept", "application/vnd.github+json")
+        // the GitHub API rejects requests without a user agent
+        .header(USER_AGENT, "fuel-faucet")
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<UserResponse>()
+        .await?;
+    let created_at = chrono::DateTime::parse_from_rfc3339(&user.created_at)?.timestamp();
+
+    Ok(Identity {
+        id: user.id,
+        login: user.login,
+        created_at: created_at.max(0) as u64,
+    })
+}
+
+fn random_token() -> String {
+    format!("{:x}", Bytes32::from(rand::thread_rng().gen::<[u8; 32]>()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -0,0 +1,49 @@
+use crate::{models::*, routes};
+use utoipa::OpenApi;
+
+/// The OpenAPI description of the faucet HTTP API, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Fuel Faucet",
+        description = "A token faucet for onboarding fuel users"
+    ),
+    paths(
+        routes::health,
+        routes::dispense_info,
+        routes::dispense_tokens,
+        routes::dispense_eligibility,
+        routes::dispense_history,
+        routes::dispense_nonce,
+        routes::sybil_report,
+        routes::sybil_unlist,
+        routes::stats,
+        routes::metrics,
+        routes::events,
+        routes::github_login,
+        routes::github_callback
+    ),
+    components(schemas(
+        HealthResponse,
+        WalletBalance,
+        DispenseInfoResponse,
+        DispenseInput,
+        RecipientType,
+        OwnershipProof,
+        NonceResponse,
+        SybilCluster,
+        SybilReport,
+        RateLimitMode,
+        FaucetEvent,
+        DispenseResponse,
+        EligibilityResponse,
+        DispenseStatus,
+        DispenseRecord,
+        DispenseHistoryResponse,
+        StatsBucket,
+        StatsResponse,
+        ErrorResponse,
+        ErrorCode
+    ))
+)]
+pub struct ApiDoc;
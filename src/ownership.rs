@@ -0,0 +1,116 @@
+//! Proof that the requester owns the recipient address: the faucet issues a nonce for the
+//! address, which the requester signs with the address' key, like any other message.
+//!
+//! Nonces carry their own expiry and an HMAC binding them to the address, so issuing them keeps
+//! no state that requests for many addresses could fill up. Only the redeemed nonces are kept,
+//! until they expire, so that each can be used once.
+
+use crate::models::OwnershipProof;
+use anyhow::anyhow;
+use fuel_crypto::{Message, Signature};
+use fuel_types::Address;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::{
+    collections::{HashSet, VecDeque},
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How long a nonce can be used for, in seconds.
+pub const NONCE_DURATION: u64 = 5 * 60;
+
+#[derive(Debug, Default)]
+struct Redeemed {
+    nonces: HashSet<String>,
+    /// When the redeemed nonces expire, in the order they were redeemed
+    by_expiry: VecDeque<(u64, String)>,
+}
+
+#[derive(Debug)]
+pub struct OwnershipChallenges {
+    /// Signs the nonces, generated anew when the faucet starts
+    key: [u8; 32],
+    redeemed: Mutex<Redeemed>,
+}
+
+impl Default for OwnershipChallenges {
+    fn default() -> Self {
+        Self {
+            key: rand::thread_rng().gen(),
+            redeemed: Mutex::default(),
+        }
+    }
+}
+
+impl OwnershipChallenges {
+    /// Issues a nonce that only a signature by `address` can redeem.
+    pub fn issue(&self, address: Address) -> String {
+        let expires_at = now() + NONCE_DURATION;
+        let salt = hex::encode(rand::thread_rng().gen::<[u8; 16]>());
+        let mac = hex::encode(self.mac(address, expires_at, &salt).finalize().into_bytes());
+        format!("{expires_at}.{salt}.{mac}")
+    }
+
+    /// Checks that the proof was signed by `address` over a nonce issued to it. Each nonce can
+    /// only be redeemed once.
+    pub fn verify(&self, address: Address, proof: &OwnershipProof) -> anyhow::Result<()> {
+        let mut parts = proof.nonce.split('.');
+        let (Some(expires_at), Some(salt), Some(mac), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow!("malformed nonce"));
+        };
+        let expires_at: u64 = expires_at.parse().map_err(|_| anyhow!("malformed nonce"))?;
+        let mac = hex::decode(mac).map_err(|_| anyhow!("malformed nonce"))?;
+        self.mac(address, expires_at, salt)
+            .verify_slice(&mac)
+            .map_err(|_| anyhow!("unknown nonce"))?;
+        let now = now();
+        if expires_at <= now {
+            return Err(anyhow!("expired nonce"));
+        }
+
+        let signature = Signature::from_str(&proof.signature)?;
+        let public_key = signature.recover(&Message::new(&proof.nonce))?;
+        let signer = Address::from(*public_key.hash());
+        if signer != address {
+            return Err(anyhow!("nonce was signed by {signer:#x}"));
+        }
+
+        let mut redeemed = self.redeemed.lock().unwrap();
+        // expired nonces are rejected before getting here, so they can be forgotten, roughly in
+        // the order they were redeemed
+        while let Some((expiry, _)) = redeemed.by_expiry.front() {
+            if *expiry > now {
+                break;
+            }
+            let (_, nonce) = redeemed.by_expiry.pop_front().expect("checked above");
+            redeemed.nonces.remove(&nonce);
+        }
+        if !redeemed.nonces.insert(proof.nonce.clone()) {
+            return Err(anyhow!("nonce was already redeemed"));
+        }
+        redeemed
+            .by_expiry
+            .push_back((expires_at, proof.nonce.clone()));
+        Ok(())
+    }
+
+    fn mac(&self, address: Address, expires_at: u64, salt: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key");
+        mac.update(address.as_ref());
+        mac.update(&expires_at.to_be_bytes());
+        mac.update(salt.as_bytes());
+        mac
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -1,76 +1,249 @@
 use crate::{
-    models::*, recaptcha, CoinOutput, SharedConfig, SharedDispenseTracker, SharedFaucetState,
-    SharedWallet,
+    config::Config,
+    dispense_tracker::RateLimitKey,
+    history::DEFAULT_PAGE_SIZE,
+    i18n::Catalogs,
+    ip_policy::IpPolicy,
+    models::*,
+    oauth::{self, SESSION_COOKIE, SESSION_DURATION},
+    openapi::ApiDoc,
+    ownership::NONCE_DURATION,
+    parse_address, recaptcha,
+    templates::{Templates, EMBED, INDEX, STATS},
+    wallet::FaucetWallet,
+    wallet_pool::PooledWallet,
+    CoinOutput, EventSender, Recipient, SharedAdaptiveLimiter, SharedCatalogs, SharedConfig,
+    SharedDispenseHistory, SharedDispenseTracker, SharedInFlightDispenses, SharedIpPolicies,
+    SharedOAuthSessions, SharedOwnershipChallenges, SharedStats, SharedSybilDetector,
+    SharedTemplates, SharedWalletPool,
 };
 use axum::{
-    response::{Html, IntoResponse, Response},
+    extract::{ConnectInfo, Path, Query, RawQuery},
+    http::{
+        header::{
+            ACCEPT, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE,
+            SET_COOKIE,
+        },
+        HeaderMap, HeaderValue, Request,
+    },
+    middleware::Next,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Redirect, Response,
+    },
     Extension, Json,
 };
 
 use fuel_core_client::client::types::NodeInfo;
 use fuel_core_client::client::FuelClient;
-use fuel_tx::{Output, UtxoId};
+use fuel_faucet_client::API_KEY_HEADER;
+use fuel_tx::{Output, TxPointer, UtxoId};
 use fuel_types::{Address, AssetId, Bytes32};
-use fuels_accounts::{wallet::WalletUnlocked, Account, ViewOnlyAccount};
+use fuels_accounts::{Account, ViewOnlyAccount};
 use fuels_core::types::transaction::{Transaction, TxPolicies};
 use fuels_core::types::transaction_builders::{BuildableTransaction, TransactionBuilder};
 use fuels_core::types::{
-    bech32::Bech32Address,
     coin::{Coin, CoinStatus},
     coin_type::CoinType,
 };
 use fuels_core::types::{input::Input, transaction_builders::ScriptTransactionBuilder};
-use handlebars::Handlebars;
+use futures::StreamExt;
 use reqwest::StatusCode;
 use secrecy::ExposeSecret;
-use serde_json::json;
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Duration;
-use std::{
-    collections::BTreeMap,
-    str::FromStr,
-    time::{SystemTime, UNIX_EPOCH},
-};
-use tracing::{error, info};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn, Instrument};
+use utoipa::{IntoParams, OpenApi};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
 
 lazy_static::lazy_static! {
     static ref START_TIME: u64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
 }
 
-#[memoize::memoize]
-pub fn render_page(public_node_url: String, captcha_key: Option<String>) -> String {
-    let template = include_str!(concat!(env!("OUT_DIR"), "/index.html"));
-    // sub in values
-    let mut handlebars = Handlebars::new();
-    handlebars
-        .register_template_string("index", template)
-        .unwrap();
-    let mut data = BTreeMap::new();
-    data.insert("page_title", "Fuel Faucet");
-    data.insert("public_node_url", public_node_url.as_str());
+/// The locale to respond in, from the `lang` query parameter or the `Accept-Language` header.
+fn locale<'a>(catalogs: &'a Catalogs, query: Option<&str>, headers: &HeaderMap) -> &'a str {
+    let lang = query.and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find_map(|(name, value)| (name == "lang").then_some(value))
+    });
+    let accept_language = headers
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|accept_language| accept_language.to_str().ok());
+    catalogs.negotiate(lang.as_deref(), accept_language)
+}
+
+/// The redirect target if its origin is allowed, so that the faucet can't be abused to send
+/// users to arbitrary sites.
+fn allowed_redirect(config: &Config, redirect_url: &str) -> Option<reqwest::Url> {
+    let url = reqwest::Url::parse(redirect_url).ok()?;
+    let origin = url.origin().ascii_serialization();
+    config
+        .redirect_allowed_origins
+        .contains(&origin)
+        .then_some(url)
+}
+
+fn render_page(
+    templates: &Templates,
+    catalogs: &Catalogs,
+    locale: &str,
+    config: &Config,
+    redirect_url: Option<&str>,
+) -> Result<String, DispenseError> {
+    let mut data = serde_json::json!({
+        "public_node_url": config.public_node_url,
+        "lang": locale,
+        "t": catalogs.page(locale, templates.vars()),
+    });
+    if let Some(redirect_url) = redirect_url.and_then(|url| allowed_redirect(config, url)) {
+        data["redirect_url"] = redirect_url.as_str().into();
+    }
     // if captcha is enabled, add captcha key
-    if let Some(captcha_key) = &captcha_key {
-        data.insert("captcha_key", captcha_key.as_str());
+    if let Some(captcha_key) = &config.captcha_key {
+        data["captcha_key"] = captcha_key.as_str().into();
+    }
+    if config.github_client_id.is_some() {
+        data["github_login"] = true.into();
     }
-    // render page
-    handlebars.render("index", &data).unwrap()
+    templates.render(INDEX, data).map_err(render_error)
 }
 
-pub async fn main(Extension(config): Extension<SharedConfig>) -> Html<String> {
-    let public_node_url = config.public_node_url.clone();
-    let captcha_key = config.captcha_key.clone();
-    Html(render_page(public_node_url, captcha_key))
+fn render_embed_page(
+    templates: &Templates,
+    catalogs: &Catalogs,
+    locale: &str,
+    config: &Config,
+) -> Result<String, DispenseError> {
+    let mut data = serde_json::json!({
+        "parent_origins": config.embed_allowed_origins.join(" "),
+        "lang": locale,
+        "t": catalogs.page(locale, templates.vars()),
+    });
+    if let Some(captcha_key) = &config.captcha_key {
+        data["captcha_key"] = captcha_key.as_str().into();
+    }
+    templates.render(EMBED, data).map_err(render_error)
 }
 
+fn render_stats_page(
+    templates: &Templates,
+    catalogs: &Catalogs,
+    locale: &str,
+    stats: &StatsResponse,
+) -> Result<String, DispenseError> {
+    let format_time = |timestamp: u64, format: &str| {
+        chrono::DateTime::from_timestamp(timestamp as i64, 0)
+            .map(|time| time.format(format).to_string())
+            .unwrap_or_default()
+    };
+    // bars are drawn relative to the busiest bucket of their chart
+    let chart = |buckets: &[StatsBucket], format: &str| {
+        let busiest = buckets.iter().map(|bucket| bucket.dispenses).max();
+        buckets
+            .iter()
+            .map(|bucket| {
+                serde_json::json!({
+                    "label": format_time(bucket.start, format),
+                    "dispenses": bucket.dispenses,
+                    "width": bucket.dispenses * 100 / busiest.unwrap_or(1).max(1),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let data = serde_json::json!({
+        "total_dispensed": stats.total_dispensed,
+        "dispenses": stats.dispenses,
+        "unique_recipients": stats.unique_recipients,
+        "balance": stats.balance.map(|balance| balance.to_string()),
+        "hourly": chart(&stats.hourly, "%H:%M"),
+        "daily": chart(&stats.daily, "%b %d"),
+        "generated_at": format_time(stats.generated_at, "%Y-%m-%d %H:%M"),
+        "lang": locale,
+        "t": catalogs.page(locale, templates.vars()),
+    });
+    templates.render(STATS, data).map_err(render_error)
+}
+
+fn render_error(e: anyhow::Error) -> DispenseError {
+    error(
+        ErrorCode::InternalError,
+        format!("Failed to render the page: {e:#}"),
+    )
+}
+
+pub async fn main(
+    RawQuery(query): RawQuery,
+    Query(page): Query<PageQuery>,
+    headers: HeaderMap,
+    Extension(config): Extension<SharedConfig>,
+    Extension(templates): Extension<SharedTemplates>,
+    Extension(catalogs): Extension<SharedCatalogs>,
+) -> Result<Html<String>, DispenseError> {
+    let locale = locale(&catalogs, query.as_deref(), &headers);
+    render_page(
+        &templates,
+        &catalogs,
+        locale,
+        &config,
+        page.redirect_url.as_deref(),
+    )
+    .map(Html)
+}
+
+/// The widget embedded by wallets and docs, which posts the outcome of dispenses to the parent
+/// window.
+pub async fn embed(
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    Extension(config): Extension<SharedConfig>,
+    Extension(templates): Extension<SharedTemplates>,
+    Extension(catalogs): Extension<SharedCatalogs>,
+) -> Result<Html<String>, DispenseError> {
+    let locale = locale(&catalogs, query.as_deref(), &headers);
+    render_embed_page(&templates, &catalogs, locale, &config).map(Html)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/health",
+    tag = "faucet",
+    responses(
+        (status = 200, description = "The faucet and its node are healthy", body = HealthResponse),
+        (status = 500, description = "The node is unreachable", body = HealthResponse),
+    )
+)]
 #[tracing::instrument(skip_all)]
-pub async fn health(Extension(wallet): Extension<SharedWallet>) -> Response {
+pub async fn health(
+    Extension(wallets): Extension<SharedWalletPool>,
+    Extension(config): Extension<SharedConfig>,
+    Extension(adaptive): Extension<SharedAdaptiveLimiter>,
+) -> Response {
     // ping client for health
-    let client = wallet
-        .provider()
-        .expect("client provider")
-        .healthy()
-        .await
-        .unwrap_or(false);
+    let provider = wallets.primary().provider().expect("client provider");
+    let client = provider.healthy().await.unwrap_or(false);
+
+    let base_asset_id = *provider.consensus_parameters().base_asset_id();
+    let mut balances = vec![];
+    for wallet in wallets.wallets() {
+        let balance = if client {
+            wallet.wallet.get_asset_balance(&base_asset_id).await.ok()
+        } else {
+            None
+        };
+        // give wallets that have been topped up another chance
+        if balance.is_some_and(|balance| balance > config.dispense_amount) {
+            wallet.set_drained(false);
+        }
+        balances.push(WalletBalance {
+            address: format!("{:#x}", Address::from(wallet.wallet.address())),
+            balance,
+        });
+    }
 
     let time = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -85,67 +258,336 @@ pub async fn health(Extension(wallet): Extension<SharedWallet>) -> Response {
 
     (
         status,
-        Json(json!({
-            "up": true,
-            "uptime": time - *START_TIME,
-            "fuel-core" : client,
-        })),
+        Json(HealthResponse {
+            up: true,
+            uptime: time - *START_TIME,
+            fuel_core: client,
+            wallets: balances,
+            rate_limit_mode: adaptive.mode(),
+        }),
     )
         .into_response()
 }
 
-impl IntoResponse for DispenseResponse {
-    fn into_response(self) -> Response {
-        (StatusCode::CREATED, Json(self)).into_response()
+#[utoipa::path(
+    get,
+    path = "/v1/stats",
+    tag = "faucet",
+    responses(
+        (status = 200, description = "Dispense statistics, rendered as a page for clients accepting HTML", body = StatsResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn stats(
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    Extension(wallets): Extension<SharedWalletPool>,
+    Extension(history): Extension<SharedDispenseHistory>,
+    Extension(stats): Extension<SharedStats>,
+    Extension(templates): Extension<SharedTemplates>,
+    Extension(catalogs): Extension<SharedCatalogs>,
+) -> Result<Response, DispenseError> {
+    let stats = stats.get(&history, &wallets).await;
+    let html = headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if html {
+        let locale = locale(&catalogs, query.as_deref(), &headers);
+        Ok(Html(render_stats_page(&templates, &catalogs, locale, &stats)?).into_response())
+    } else {
+        Ok(Json(stats).into_response())
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/metrics",
+    tag = "faucet",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn metrics(Extension(adaptive): Extension<SharedAdaptiveLimiter>) -> Response {
+    let mode = adaptive.mode();
+    let totals = adaptive.totals();
+
+    let mut body = String::new();
+    body.push_str("# HELP faucet_rate_limit_mode Whether the rate limits are in the given mode.\n");
+    body.push_str("# TYPE faucet_rate_limit_mode gauge\n");
+    for (name, value) in [
+        ("normal", RateLimitMode::Normal),
+        ("elevated", RateLimitMode::Elevated),
+        ("strict", RateLimitMode::Strict),
+    ] {
+        body.push_str(&format!(
+            "faucet_rate_limit_mode{{mode=\"{name}\"}} {}\n",
+            u8::from(mode == value)
+        ));
+    }
+    body.push_str("# HELP faucet_dispense_rate Dispense requests within the last minute.\n");
+    body.push_str("# TYPE faucet_dispense_rate gauge\n");
+    body.push_str(&format!("faucet_dispense_rate {}\n", adaptive.rate()));
+    body.push_str("# HELP faucet_dispense_requests_total Dispense requests by outcome.\n");
+    body.push_str("# TYPE faucet_dispense_requests_total counter\n");
+    for (outcome, count) in [
+        ("dispensed", totals.dispensed),
+        ("rejected", totals.rejected),
+        ("failed", totals.failed),
+    ] {
+        body.push_str(&format!(
+            "faucet_dispense_requests_total{{outcome=\"{outcome}\"}} {count}\n"
+        ));
+    }
+
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "faucet",
+    responses(
+        (status = 200, description = "A stream of server-sent events, named after the type of the event", body = FaucetEvent, content_type = "text/event-stream"),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn events(
+    Extension(events): Extension<EventSender>,
+    Extension(in_flight): Extension<SharedInFlightDispenses>,
+) -> impl IntoResponse {
+    let stream = futures::stream::unfold(events.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let event = truncated(event);
+                    let sse = Event::default().event(event.name()).json_data(&event);
+                    return Some((sse, receiver));
+                }
+                // subscribers that fall behind miss events rather than holding up the others
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    // connections are only closed once their response ends, which shutdown waits for
+    .take_until(async move { in_flight.shutdown_started().await });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Feeds the outcome of every dispense request to the adaptive rate limiter.
+pub async fn track_dispense_outcome<B>(request: Request<B>, next: Next<B>) -> Response {
+    let adaptive = request.extensions().get::<SharedAdaptiveLimiter>().cloned();
+    let response = next.run(request).await;
+    if let Some(adaptive) = adaptive {
+        adaptive.record(response.status());
+    }
+    response
+}
+
+/// Translates the messages of errors into the locale of the request. English responses keep
+/// the messages of the error codes.
+pub async fn localize_errors<B>(request: Request<B>, next: Next<B>) -> Response {
+    let catalogs = request.extensions().get::<SharedCatalogs>().cloned();
+    let locale = catalogs
+        .as_ref()
+        .map(|catalogs| locale(catalogs, request.uri().query(), request.headers()).to_string());
+    let response = next.run(request).await;
+
+    let (Some(catalogs), Some(locale)) = (catalogs, locale) else {
+        return response;
+    };
+    let Some(code) = response.extensions().get::<ErrorCode>().copied() else {
+        return response;
+    };
+    let Some(message) = catalogs.error_message(&locale, code) else {
+        return response;
+    };
+    // keeps the status and headers of the error, e.g. `Retry-After`
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    let body = Json(ErrorResponse {
+        code,
+        error: message.to_string(),
+    })
+    .into_response()
+    .into_body();
+    Response::from_parts(parts, body)
+}
+
+pub async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
 impl IntoResponse for DispenseError {
     fn into_response(self) -> Response {
-        (
+        let mut response = (
             self.status,
-            Json(json!({
-                "error": self.error
-            })),
+            Json(ErrorResponse {
+                code: self.code,
+                error: self.error,
+            }),
         )
-            .into_response()
+            .into_response();
+        // lets the error be translated on its way out
+        response.extensions_mut().insert(self.code);
+        response
     }
 }
 
-impl IntoResponse for DispenseInfoResponse {
-    fn into_response(self) -> Response {
-        (StatusCode::OK, Json(self)).into_response()
-    }
+fn parse_recipient(
+    recipient: &str,
+    recipient_type: RecipientType,
+) -> Result<Recipient, DispenseError> {
+    Recipient::parse(recipient, recipient_type).ok_or_else(|| {
+        error(
+            ErrorCode::InvalidAddress,
+            format!("invalid {recipient_type:?} recipient: {recipient}"),
+        )
+    })
+}
+
+/// A dispense request that passed validation.
+struct Claim {
+    recipient: Recipient,
+    amount: u64,
+    /// What the amount is accounted against
+    limit_key: RateLimitKey,
+    budget: u64,
+    /// The interval the budget is accounted over
+    interval: u64,
 }
 
 fn check_and_mark_dispense_limit(
     dispense_tracker: &SharedDispenseTracker,
-    address: Address,
-    interval: u64,
+    claim: &Claim,
 ) -> Result<(), DispenseError> {
     let mut tracker = dispense_tracker.lock().unwrap();
-    tracker.evict_expired_entries(interval);
+    tracker.evict_expired_entries(claim.interval);
 
-    if tracker.has_tracked(&address) {
+    let key = &claim.limit_key;
+    let spent = tracker.spent(key);
+    if spent.saturating_add(claim.amount) > claim.budget {
         return Err(error(
-            "Account has already received assets today".to_string(),
-            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::RateLimited,
+            format!("{key} has already received {spent} of its budget"),
         ));
     }
 
-    if tracker.is_in_progress(&address) {
+    if tracker.is_in_progress(key) {
         return Err(error(
-            "Account is already in the process of receiving assets".to_string(),
-            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::InProgress,
+            format!("{key} is already in the process of receiving assets"),
         ));
     }
 
-    tracker.mark_in_progress(address);
+    tracker.mark_in_progress(key.clone());
     Ok(())
 }
 
+/// Whether the request comes with one of the configured API keys.
+fn has_api_key(headers: &HeaderMap, config: &Config) -> bool {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|key| key.to_str().ok())
+        .is_some_and(|key| {
+            config
+                .api_keys
+                .iter()
+                .any(|api_key| api_key.expose_secret() == key)
+        })
+}
+
+/// The address of the client, as reported by the trusted proxies in front of the faucet.
+fn client_ip(headers: &HeaderMap, peer: SocketAddr, trusted_proxy_count: usize) -> IpAddr {
+    if trusted_proxy_count == 0 {
+        return peer.ip();
+    }
+
+    // each proxy appends the address it received the request from, so the entries before the
+    // ones added by trusted proxies may be forged
+    let forwarded: Vec<IpAddr> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+    forwarded
+        .len()
+        .checked_sub(trusted_proxy_count)
+        .and_then(|index| forwarded.get(index))
+        .copied()
+        .unwrap_or(peer.ip())
+}
+
+fn recipient_id(recipient: Recipient) -> String {
+    match recipient {
+        Recipient::Address(address) => format!("{address:#x}"),
+        Recipient::Contract(contract_id) => format!("{contract_id:#x}"),
+    }
+}
+
+/// Shortens the recipient for the public activity feed, e.g. `0x1234…cdef`.
+fn truncated(event: FaucetEvent) -> FaucetEvent {
+    let truncate = |id: String| format!("{}…{}", &id[..6], &id[id.len() - 4..]);
+    match event {
+        FaucetEvent::DispenseSubmitted {
+            recipient,
+            tx_id,
+            amount,
+        } => FaucetEvent::DispenseSubmitted {
+            recipient: truncate(recipient),
+            tx_id,
+            amount,
+        },
+        FaucetEvent::DispenseCommitted {
+            recipient,
+            tx_id,
+            amount,
+        } => FaucetEvent::DispenseCommitted {
+            recipient: truncate(recipient),
+            tx_id,
+            amount,
+        },
+        FaucetEvent::DispenseFailed {
+            recipient,
+            amount,
+            code,
+        } => FaucetEvent::DispenseFailed {
+            recipient: truncate(recipient),
+            amount,
+            code,
+        },
+        event @ FaucetEvent::BalanceChanged { .. } => event,
+    }
+}
+
+/// The token of the session the request was made with, either as a bearer token or a cookie.
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    bearer.or_else(|| {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .find_map(|cookie| {
+                cookie
+                    .trim()
+                    .strip_prefix(SESSION_COOKIE)
+                    .and_then(|cookie| cookie.strip_prefix('='))
+            })
+    })
+}
+
 async fn get_coins(
-    wallet: &WalletUnlocked,
+    wallet: &FaucetWallet,
     base_asset_id: &AssetId,
     amount: u64,
 ) -> Result<Vec<Input>, DispenseError> {
@@ -154,8 +596,8 @@ async fn get_coins(
         .await
         .map_err(|e| {
             error(
+                ErrorCode::InsufficientFunds,
                 format!("Failed to get resources: {e}"),
-                StatusCode::INTERNAL_SERVER_ERROR,
             )
         })
         .map(|resources| resources.into_iter().map(Input::resource_signed).collect())
@@ -174,57 +616,255 @@ async fn submit_tx_with_timeout(
     .map(|r| {
         r.map_err(|e| {
             error(
+                ErrorCode::SubmitFailed,
                 format!("Failed to submit transaction with error: {e}"),
-                StatusCode::INTERNAL_SERVER_ERROR,
             )
         })
     })
     .map_err(|e| {
         error(
+            ErrorCode::SubmitTimeout,
             format!("Got a timeout during transaction submission: {e}"),
-            StatusCode::INTERNAL_SERVER_ERROR,
         )
     })??;
 
     Ok(())
 }
 
+#[utoipa::path(
+    post,
+    path = "/v1/dispense",
+    tag = "faucet",
+    request_body = DispenseInput,
+    responses(
+        (status = 201, description = "Tokens were dispensed", body = DispenseResponse),
+        (status = 400, description = "Invalid address or amount", body = ErrorResponse),
+        (status = 401, description = "Captcha or ownership verification failed", body = ErrorResponse),
+        (status = 403, description = "The address is denylisted", body = ErrorResponse),
+        (status = 429, description = "The address has used up its budget", body = ErrorResponse),
+        (status = 500, description = "The dispense failed", body = ErrorResponse),
+        (status = 503, description = "The faucet is unavailable", body = ErrorResponse),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip_all)]
 pub async fn dispense_tokens(
+    headers: HeaderMap,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     Json(input): Json<DispenseInput>,
-    Extension(wallet): Extension<SharedWallet>,
-    Extension(state): Extension<SharedFaucetState>,
+    Extension(wallets): Extension<SharedWalletPool>,
     Extension(config): Extension<SharedConfig>,
     Extension(info_node): Extension<Arc<NodeInfo>>,
     Extension(client): Extension<Arc<FuelClient>>,
     Extension(dispense_tracker): Extension<SharedDispenseTracker>,
-) -> Result<DispenseResponse, DispenseError> {
+    Extension(in_flight): Extension<SharedInFlightDispenses>,
+    Extension(sessions): Extension<SharedOAuthSessions>,
+    Extension(challenges): Extension<SharedOwnershipChallenges>,
+    Extension(sybil): Extension<SharedSybilDetector>,
+    Extension(adaptive): Extension<SharedAdaptiveLimiter>,
+    Extension(ip_policies): Extension<SharedIpPolicies>,
+    Extension(events): Extension<EventSender>,
+    Extension(history): Extension<SharedDispenseHistory>,
+) -> Result<(StatusCode, Json<DispenseResponse>), DispenseError> {
+    if in_flight.is_shutting_down() {
+        return Err(ErrorCode::ShuttingDown.into());
+    }
+
     // parse deposit address
-    let address = if let Ok(address) = Address::from_str(input.address.as_str()) {
-        Ok(address)
-    } else if let Ok(address) = Bech32Address::from_str(input.address.as_str()) {
-        Ok(address.into())
-    } else {
+    let recipient = parse_recipient(input.address.as_str(), input.recipient_type)?;
+    if let Recipient::Address(address) = recipient {
+        if sybil.is_denylisted(&address) {
+            return Err(error(
+                ErrorCode::Denylisted,
+                format!("{recipient} was detected forwarding dispensed funds"),
+            ));
+        }
+    }
+
+    let has_api_key = has_api_key(&headers, &config);
+
+    // the limits are tightened while traffic is anomalous, and for restricted networks, which
+    // API key holders are exempt from
+    let mut limits = adaptive.limits(&config);
+    let ip = client_ip(&headers, peer, config.trusted_proxy_count);
+    match ip_policies.policy(ip).filter(|_| !has_api_key) {
+        Some(IpPolicy::Block) => {
+            return Err(error(
+                ErrorCode::NetworkBlocked,
+                format!("{ip} is in a blocked network"),
+            ))
+        }
+        Some(IpPolicy::Captcha) => limits.captcha_required = true,
+        Some(IpPolicy::Reduce(max_amount)) => {
+            limits.dispense_amount = limits.dispense_amount.min(max_amount);
+            limits.max_dispense_amount = limits.max_dispense_amount.min(max_amount);
+        }
+        Some(IpPolicy::Allow) | None => {}
+    }
+
+    let amount = input.amount.unwrap_or(limits.dispense_amount);
+    if amount == 0 || amount > limits.max_dispense_amount {
+        return Err(error(
+            ErrorCode::InvalidAmount,
+            format!(
+                "requested {amount}, expected at most {}",
+                limits.max_dispense_amount
+            ),
+        ));
+    }
+
+    // owners that signed a nonce issued to their address get a larger budget
+    let verified = match (&input.proof, recipient) {
+        (Some(proof), Recipient::Address(address)) => {
+            challenges.verify(address, proof).map_err(|e| {
+                error(
+                    ErrorCode::InvalidProof,
+                    format!("ownership of {recipient} could not be verified: {e}"),
+                )
+            })?;
+            true
+        }
+        (Some(_), Recipient::Contract(_)) => {
+            return Err(error(
+                ErrorCode::InvalidProof,
+                "contracts can't prove ownership".to_string(),
+            ))
+        }
+        (None, _) => false,
+    };
+    if config.require_ownership_proof && !verified && !has_api_key {
         return Err(error(
-            "invalid address".to_string(),
-            StatusCode::BAD_REQUEST,
+            ErrorCode::ProofRequired,
+            format!(
+                "sign a nonce from /v1/dispense/nonce/{} with its key",
+                input.address
+            ),
         ));
-    }?;
+    }
+
+    // signed in users are trusted with a larger budget, accounted against their account
+    let identity = session_token(&headers).and_then(|token| sessions.identity(token));
+    let claim = match &identity {
+        Some(identity) => Claim {
+            recipient,
+            amount,
+            limit_key: RateLimitKey::Account(format!("github:{}", identity.id)),
+            budget: limits.identity_dispense_budget,
+            interval: limits.interval,
+        },
+        None => Claim {
+            recipient,
+            amount,
+            limit_key: RateLimitKey::Recipient(recipient),
+            budget: if verified {
+                limits.verified_dispense_budget
+            } else {
+                limits.dispense_budget
+            },
+            interval: limits.interval,
+        },
+    };
 
-    // verify captcha
-    if let Some(s) = config.captcha_secret.clone() {
+    // verify captcha, unless the request comes with a valid API key or from a signed in user,
+    // who have to solve it as well while limits are tightened or from a restricted network
+    if let (Some(s), false) = (
+        config.captcha_secret.clone(),
+        has_api_key || (identity.is_some() && !limits.captcha_required),
+    ) {
         recaptcha::verify(s.expose_secret(), input.captcha.as_str(), None)
             .await
-            .map_err(|e| {
-                tracing::error!("{}", e);
-                DispenseError {
-                    error: "captcha failed".to_string(),
-                    status: StatusCode::UNAUTHORIZED,
-                }
-            })?;
+            .map_err(|e| error(ErrorCode::CaptchaFailed, format!("captcha failed: {e}")))?;
     }
 
-    check_and_mark_dispense_limit(&dispense_tracker, address, config.dispense_limit_interval)?;
+    // invalid redirects are dropped rather than failing the dispense
+    let redirect_url = input
+        .redirect_url
+        .as_deref()
+        .and_then(|url| allowed_redirect(&config, url));
+
+    // Run the dispense in its own task so it isn't cancelled halfway through when the request
+    // is dropped, and keep it registered as in-flight so that shutdown waits for it to finish.
+    let guard = in_flight.start();
+    let response = tokio::spawn(
+        async move {
+            let _guard = guard;
+            let recipient = claim.recipient;
+            let response = dispense(
+                claim,
+                wallets,
+                config,
+                info_node,
+                client,
+                dispense_tracker,
+                events,
+                history,
+            )
+            .await;
+            // follow where the funds go next
+            if let (Ok(_), Recipient::Address(address)) = (&response, recipient) {
+                sybil.record(address);
+            }
+            response
+        }
+        .in_current_span(),
+    )
+    .await
+    .map_err(|e| {
+        error(
+            ErrorCode::InternalError,
+            format!("Dispense task failed: {e}"),
+        )
+    })??;
+
+    // the page sends the user back with the id of the transaction
+    let redirect_url = redirect_url.map(|mut url| {
+        let params: Vec<_> = url
+            .query_pairs()
+            .filter(|(name, _)| name != "tx_id")
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        url.query_pairs_mut()
+            .clear()
+            .extend_pairs(params)
+            .append_pair("tx_id", &format!("0x{}", response.tx_id));
+        url.to_string()
+    });
+
+    Ok((
+        StatusCode::CREATED,
+        Json(DispenseResponse {
+            redirect_url,
+            ..response
+        }),
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn dispense(
+    claim: Claim,
+    wallets: SharedWalletPool,
+    config: SharedConfig,
+    info_node: Arc<NodeInfo>,
+    client: Arc<FuelClient>,
+    dispense_tracker: SharedDispenseTracker,
+    events: EventSender,
+    history: SharedDispenseHistory,
+) -> Result<DispenseResponse, DispenseError> {
+    check_and_mark_dispense_limit(&dispense_tracker, &claim)?;
+    let (recipient, amount) = (claim.recipient, claim.amount);
+    // sending only fails when nobody is subscribed
+    let publish = |event| {
+        let _ = events.send(event);
+    };
+    let failed = |e: DispenseError, tx_id: Option<String>| {
+        history.record(recipient, amount, tx_id, Some(e.code));
+        publish(FaucetEvent::DispenseFailed {
+            recipient: recipient_id(recipient),
+            amount,
+            code: e.code,
+        });
+        e
+    };
 
     struct CleanUpper<Fn>(Fn)
     where
@@ -244,17 +884,116 @@ pub async fn dispense_tokens(
         dispense_tracker
             .lock()
             .unwrap()
-            .remove_in_progress(&address);
+            .remove_in_progress(&claim.limit_key);
     });
 
-    let provider = wallet.provider().expect("client provider");
+    // Fall back to the other wallets if one of them runs out of funds
+    let mut result = Err(ErrorCode::InsufficientFunds.into());
+    let mut used_wallet = None;
+    for _ in 0..wallets.len() {
+        let wallet = wallets.acquire();
+        result = submit_dispense(recipient, amount, &wallet, &config, &info_node).await;
+        match &result {
+            Err(e) if e.code == ErrorCode::InsufficientFunds => {
+                warn!(
+                    "faucet wallet {:#x} is drained",
+                    Address::from(wallet.wallet.address())
+                );
+                wallet.set_drained(true);
+            }
+            Ok(_) => {
+                wallet.set_drained(false);
+                used_wallet = Some(wallet);
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+    let tx_id = result.map_err(|e| failed(e, None))?;
+    publish(FaucetEvent::DispenseSubmitted {
+        recipient: recipient_id(recipient),
+        tx_id: tx_id.to_string(),
+        amount,
+    });
+
+    submit_tx_with_timeout(&client, &tx_id, config.timeout)
+        .await
+        .map_err(|e| failed(e, Some(tx_id.to_string())))?;
+
+    info!("dispensed {} tokens to {}", amount, recipient);
+    history.record(recipient, amount, Some(tx_id.to_string()), None);
+    publish(FaucetEvent::DispenseCommitted {
+        recipient: recipient_id(recipient),
+        tx_id: tx_id.to_string(),
+        amount,
+    });
+
+    dispense_tracker
+        .lock()
+        .unwrap()
+        .track(claim.limit_key.clone(), amount);
+
+    let base_asset_id = *wallets
+        .primary()
+        .provider()
+        .expect("client provider")
+        .consensus_parameters()
+        .base_asset_id();
+    let balance = match recipient {
+        Recipient::Address(address) => client.balance(&address, Some(&base_asset_id)).await,
+        Recipient::Contract(contract_id) => {
+            client
+                .contract_balance(&contract_id, Some(&base_asset_id))
+                .await
+        }
+    };
+    let balance = match balance {
+        Ok(balance) => Some(balance),
+        Err(e) => {
+            warn!("failed to look up the balance of {recipient}: {e}");
+            None
+        }
+    };
+
+    // only look the balance of the faucet wallet up if someone is listening
+    if let Some(wallet) = used_wallet.filter(|_| events.receiver_count() > 0) {
+        if let Ok(balance) = wallet.wallet.get_asset_balance(&base_asset_id).await {
+            publish(FaucetEvent::BalanceChanged {
+                wallet: format!("{:#x}", Address::from(wallet.wallet.address())),
+                balance,
+            });
+        }
+    }
+
+    Ok(DispenseResponse {
+        status: "Success".to_string(),
+        tokens: amount,
+        tx_id: tx_id.to_string(),
+        explorer_url: config
+            .explorer_tx_url
+            .as_ref()
+            .map(|url| url.replace("{tx_id}", &format!("{tx_id:#x}"))),
+        balance,
+        redirect_url: None,
+    })
+}
+
+/// Builds and submits a dispense transaction from `wallet`, chained on its previous dispense.
+async fn submit_dispense(
+    recipient: Recipient,
+    amount: u64,
+    wallet: &PooledWallet,
+    config: &SharedConfig,
+    info_node: &NodeInfo,
+) -> Result<Bytes32, DispenseError> {
+    let provider = wallet.wallet.provider().expect("client provider");
     let base_asset_id = *provider.consensus_parameters().base_asset_id();
 
     let mut tx_id = None;
     for _ in 0..config.number_of_retries {
-        let mut guard = state.lock().await;
-        let amount = guard.last_output.as_ref().map_or(0, |o| o.amount);
-        let inputs = if amount > config.dispense_amount {
+        let mut guard = wallet.state.lock().await;
+        let last_amount = guard.last_output.as_ref().map_or(0, |o| o.amount);
+        let inputs = if last_amount > amount {
             let previous_coin_output = guard.last_output.expect("Checked above");
             let coin_type = CoinType::Coin(Coin {
                 amount: previous_coin_output.amount,
@@ -268,65 +1007,102 @@ pub async fn dispense_tokens(
             vec![Input::resource_signed(coin_type)]
         } else {
             get_coins(
-                &wallet,
+                &wallet.wallet,
                 &base_asset_id,
-                // Double the target amount to cover also the fee
-                config.dispense_amount * info_node.max_depth * 2,
+                // Enough for this dispense and a chain of default ones following it, doubled to
+                // also cover the fee
+                amount
+                    .saturating_add(config.dispense_amount.saturating_mul(info_node.max_depth))
+                    .saturating_mul(2),
             )
             .await?
         };
 
-        let recipient_address = address;
-        let faucet_address: Address = wallet.address().into();
-        let outputs = vec![
-            Output::coin(recipient_address, config.dispense_amount, base_asset_id),
-            // Sends the dust change to the user
-            Output::change(recipient_address, 0, base_asset_id),
-            // Add an additional output to store the stable part of the fee change.
-            Output::coin(faucet_address, 0, base_asset_id),
-        ];
-
-        let tip = guard.next_tip();
-
-        let mut tx_builder = ScriptTransactionBuilder::prepare_transfer(
-            inputs,
-            outputs,
-            TxPolicies::default().with_tip(tip),
-        );
+        let faucet_address: Address = wallet.wallet.address().into();
+        let tx_policies = TxPolicies::default().with_tip(guard.next_tip());
+        // The last output stores the stable part of the fee change, chained into the next
+        // dispense, so it must stay at the same index for either kind of recipient.
+        let mut tx_builder = match recipient {
+            Recipient::Address(recipient_address) => {
+                let outputs = vec![
+                    Output::coin(recipient_address, amount, base_asset_id),
+                    // Sends the dust change to the user
+                    Output::change(recipient_address, 0, base_asset_id),
+                    Output::coin(faucet_address, 0, base_asset_id),
+                ];
+                ScriptTransactionBuilder::prepare_transfer(inputs, outputs, tx_policies)
+            }
+            Recipient::Contract(contract_id) => {
+                // Contracts can only receive assets through a transfer made by a script, which
+                // needs the contract as an input. Contract inputs must come first so that
+                // `adjust_for_fee` doesn't shift them.
+                let zeroes = Bytes32::zeroed();
+                let inputs = std::iter::once(Input::contract(
+                    UtxoId::new(zeroes, 0),
+                    zeroes,
+                    zeroes,
+                    TxPointer::default(),
+                    contract_id,
+                ))
+                .chain(inputs)
+                .collect();
+                let outputs = vec![
+                    Output::contract(0, zeroes, zeroes),
+                    Output::change(faucet_address, 0, base_asset_id),
+                    Output::coin(faucet_address, 0, base_asset_id),
+                ];
+                ScriptTransactionBuilder::prepare_contract_transfer(
+                    contract_id,
+                    amount,
+                    base_asset_id,
+                    inputs,
+                    outputs,
+                    tx_policies,
+                )
+            }
+        };
 
         wallet
+            .wallet
             .add_witnesses(&mut tx_builder)
             .expect("Valid witness");
         wallet
-            .adjust_for_fee(&mut tx_builder, config.dispense_amount)
+            .wallet
+            .adjust_for_fee(&mut tx_builder, amount)
             .await
             .map_err(|e| {
                 error(
+                    ErrorCode::InternalError,
                     format!("Failed to adjust for fee: {e}"),
-                    StatusCode::INTERNAL_SERVER_ERROR,
                 )
             })?;
 
         let max_fee = tx_builder.estimate_max_fee(provider).await.map_err(|e| {
             error(
+                ErrorCode::InternalError,
                 format!("Error calculating `TransactionFee`: {e}"),
-                StatusCode::INTERNAL_SERVER_ERROR,
             )
         })?;
         let available_balance = available_balance(&tx_builder.inputs, &base_asset_id);
         let stable_fee_change = available_balance
-            .checked_sub(max_fee.saturating_add(config.dispense_amount))
+            .checked_sub(max_fee.saturating_add(amount))
             .ok_or_else(|| {
                 error(
+                    ErrorCode::InsufficientFunds,
                     "Not enough asset to cover a max fee".to_string(),
-                    StatusCode::INTERNAL_SERVER_ERROR,
                 )
             })?;
 
         *tx_builder.outputs.last_mut().unwrap() =
             Output::coin(faucet_address, stable_fee_change, base_asset_id);
 
-        let script = tx_builder.build(provider).await.expect("Valid script");
+        // signing happens while building, which can fail with a remote signer
+        let script = tx_builder.build(provider).await.map_err(|e| {
+            error(
+                ErrorCode::InternalError,
+                format!("Failed to sign the transaction for {recipient}: {e}"),
+            )
+        })?;
 
         let id = script.id(provider.chain_id());
         let result = tokio::time::timeout(
@@ -336,18 +1112,18 @@ pub async fn dispense_tokens(
         .await
         .map_err(|_| {
             error(
-                format!("Timeout while submitting transaction for address: {address:X}"),
-                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorCode::SubmitTimeout,
+                format!("Timeout while submitting transaction for {recipient}"),
             )
         })
         .and_then(|r| {
             r.map_err(|e| {
                 error(
+                    ErrorCode::SubmitFailed,
                     format!(
-                        "Failed to submit transaction for address: {address:X} with error: {}",
+                        "Failed to submit transaction for {recipient} with error: {}",
                         e
                     ),
-                    StatusCode::INTERNAL_SERVER_ERROR,
                 )
             })
         });
@@ -369,47 +1145,353 @@ pub async fn dispense_tokens(
         };
     }
 
-    let Some(tx_id) = tx_id else {
-        return Err(error(
-            "Failed to submit transaction".to_string(),
-            StatusCode::INTERNAL_SERVER_ERROR,
-        ));
-    };
+    tx_id.ok_or_else(|| {
+        error(
+            ErrorCode::SubmitFailed,
+            format!("Failed to submit transaction for {recipient}"),
+        )
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/dispense",
+    tag = "faucet",
+    responses(
+        (status = 200, description = "The amount and asset dispensed", body = DispenseInfoResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn dispense_info(
+    Extension(config): Extension<SharedConfig>,
+    Extension(wallets): Extension<SharedWalletPool>,
+    Extension(adaptive): Extension<SharedAdaptiveLimiter>,
+) -> Result<Json<DispenseInfoResponse>, DispenseError> {
+    let provider = wallets.primary().provider().expect("client provider");
+    let base_asset_id = *provider.consensus_parameters().base_asset_id();
+    let limits = adaptive.limits(&config);
 
-    submit_tx_with_timeout(&client, &tx_id, config.timeout).await?;
+    Ok(Json(DispenseInfoResponse {
+        amount: limits.dispense_amount,
+        asset_id: base_asset_id.to_string(),
+        max_amount: limits.max_dispense_amount,
+        budget: limits.dispense_budget,
+        interval: limits.interval,
+    }))
+}
 
-    info!(
-        "dispensed {} tokens to {:#x}",
-        config.dispense_amount, &address
-    );
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EligibilityQuery {
+    /// Whether the path holds an address or a contract id
+    #[serde(default)]
+    #[param(inline)]
+    recipient_type: RecipientType,
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/dispense/eligibility/{address}",
+    tag = "faucet",
+    params(
+        ("address" = String, Path, description = "Recipient address or contract id, either hex or bech32 encoded"),
+        EligibilityQuery,
+    ),
+    responses(
+        (status = 200, description = "Whether the address can receive assets", body = EligibilityResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn dispense_eligibility(
+    Path(address): Path<String>,
+    Query(query): Query<EligibilityQuery>,
+    Extension(config): Extension<SharedConfig>,
+    Extension(dispense_tracker): Extension<SharedDispenseTracker>,
+    Extension(adaptive): Extension<SharedAdaptiveLimiter>,
+) -> Result<Json<EligibilityResponse>, DispenseError> {
+    let recipient = parse_recipient(address.as_str(), query.recipient_type)?;
+    let limits = adaptive.limits(&config);
 
     let mut tracker = dispense_tracker.lock().unwrap();
-    tracker.track(address);
+    tracker.evict_expired_entries(limits.interval);
 
-    Ok(DispenseResponse {
-        status: "Success".to_string(),
-        tokens: config.dispense_amount,
-        tx_id: tx_id.to_string(),
+    let key = RateLimitKey::Recipient(recipient);
+    let remaining = limits.dispense_budget.saturating_sub(tracker.spent(&key));
+    // a default dispense has to fit, though a smaller `amount` may still be requested
+    let code = if remaining < limits.dispense_amount {
+        Some(ErrorCode::RateLimited)
+    } else if tracker.is_in_progress(&key) {
+        Some(ErrorCode::InProgress)
+    } else {
+        None
+    };
+
+    Ok(Json(EligibilityResponse {
+        eligible: code.is_none(),
+        code,
+        remaining,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    /// Where to send the user once the dispense is done
+    #[serde(rename = "redirectUrl")]
+    redirect_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Whether the path holds an address or a contract id
+    #[serde(default)]
+    #[param(inline)]
+    recipient_type: RecipientType,
+    /// The `next_cursor` of the previous page, to get older dispenses
+    cursor: Option<u64>,
+    /// The number of dispenses per page, at most 100
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/dispense/history/{address}",
+    tag = "faucet",
+    params(
+        ("address" = String, Path, description = "Recipient address or contract id, either hex or bech32 encoded"),
+        HistoryQuery,
+    ),
+    responses(
+        (status = 200, description = "The past dispenses to the address", body = DispenseHistoryResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 500, description = "The history file couldn't be read", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn dispense_history(
+    Path(address): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    Extension(history): Extension<SharedDispenseHistory>,
+) -> Result<Json<DispenseHistoryResponse>, DispenseError> {
+    let recipient = parse_recipient(address.as_str(), query.recipient_type)?;
+    // older pages are read from the history file
+    let (records, next_cursor) = tokio::task::spawn_blocking(move || {
+        history.page(
+            &recipient,
+            query.cursor,
+            query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
     })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|page| page)
+    .map_err(|e| {
+        error(
+            ErrorCode::InternalError,
+            format!("Failed to read the dispense history: {e}"),
+        )
+    })?;
+
+    Ok(Json(DispenseHistoryResponse {
+        records,
+        next_cursor,
+    }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/v1/dispense/nonce/{address}",
+    tag = "faucet",
+    params(
+        ("address" = String, Path, description = "Recipient address, either hex or bech32 encoded"),
+    ),
+    responses(
+        (status = 200, description = "A nonce to sign with the key of the address", body = NonceResponse),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 403, description = "The request comes from a blocked network", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(skip_all)]
-pub async fn dispense_info(
+pub async fn dispense_nonce(
+    headers: HeaderMap,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(address): Path<String>,
     Extension(config): Extension<SharedConfig>,
-    Extension(wallet): Extension<SharedWallet>,
-) -> Result<DispenseInfoResponse, DispenseError> {
-    let provider = wallet.provider().expect("client provider");
-    let base_asset_id = *provider.consensus_parameters().base_asset_id();
+    Extension(ip_policies): Extension<SharedIpPolicies>,
+    Extension(challenges): Extension<SharedOwnershipChallenges>,
+) -> Result<Json<NonceResponse>, DispenseError> {
+    let address = parse_address(address.as_str()).ok_or_else(|| {
+        error(
+            ErrorCode::InvalidAddress,
+            format!("invalid address: {address}"),
+        )
+    })?;
 
-    Ok(DispenseInfoResponse {
-        amount: config.dispense_amount,
-        asset_id: base_asset_id.to_string(),
-    })
+    // networks that can't dispense have no use for nonces either
+    let ip = client_ip(&headers, peer, config.trusted_proxy_count);
+    if let (Some(IpPolicy::Block), false) = (ip_policies.policy(ip), has_api_key(&headers, &config))
+    {
+        return Err(error(
+            ErrorCode::NetworkBlocked,
+            format!("{ip} is in a blocked network"),
+        ));
+    }
+
+    Ok(Json(NonceResponse {
+        nonce: challenges.issue(address),
+        expires_in: NONCE_DURATION,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/admin/sybil",
+    tag = "faucet",
+    params(
+        ("X-API-Key" = String, Header, description = "One of the faucet's API keys"),
+    ),
+    responses(
+        (status = 200, description = "The detected clusters and denylisted addresses", body = SybilReport),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn sybil_report(
+    headers: HeaderMap,
+    Extension(config): Extension<SharedConfig>,
+    Extension(sybil): Extension<SharedSybilDetector>,
+) -> Result<Json<SybilReport>, DispenseError> {
+    if !has_api_key(&headers, &config) {
+        return Err(ErrorCode::ApiKeyRequired.into());
+    }
+
+    Ok(Json(sybil.report()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/sybil/{address}",
+    tag = "faucet",
+    params(
+        ("address" = String, Path, description = "Address to unlist, either hex or bech32 encoded"),
+        ("X-API-Key" = String, Header, description = "One of the faucet's API keys"),
+    ),
+    responses(
+        (status = 200, description = "The report once the address is unlisted", body = SybilReport),
+        (status = 400, description = "Invalid address", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn sybil_unlist(
+    Path(address): Path<String>,
+    headers: HeaderMap,
+    Extension(config): Extension<SharedConfig>,
+    Extension(sybil): Extension<SharedSybilDetector>,
+) -> Result<Json<SybilReport>, DispenseError> {
+    if !has_api_key(&headers, &config) {
+        return Err(ErrorCode::ApiKeyRequired.into());
+    }
+    let address = parse_address(address.as_str()).ok_or_else(|| {
+        error(
+            ErrorCode::InvalidAddress,
+            format!("invalid address: {address}"),
+        )
+    })?;
+
+    if sybil.unlist(address) {
+        info!("Unlisted {address:#x}");
+    }
+    Ok(Json(sybil.report()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/auth/github",
+    tag = "faucet",
+    responses(
+        (status = 303, description = "Redirects to GitHub to sign in"),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn github_login(
+    Extension(config): Extension<SharedConfig>,
+    Extension(sessions): Extension<SharedOAuthSessions>,
+) -> Result<Redirect, DispenseError> {
+    let state = sessions.start_login();
+    let url = oauth::authorize_url(&config, &state)
+        .map_err(|e| error(ErrorCode::LoginFailed, format!("{e}")))?;
+    Ok(Redirect::to(url.as_str()))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OAuthCallbackQuery {
+    code: String,
+    state: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/auth/github/callback",
+    tag = "faucet",
+    params(OAuthCallbackQuery),
+    responses(
+        (status = 303, description = "Signed in, the session is set as a cookie"),
+        (status = 401, description = "Sign in failed", body = ErrorResponse),
+        (status = 403, description = "The account is too new", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn github_callback(
+    Query(query): Query<OAuthCallbackQuery>,
+    Extension(config): Extension<SharedConfig>,
+    Extension(sessions): Extension<SharedOAuthSessions>,
+) -> Result<Response, DispenseError> {
+    if !sessions.finish_login(&query.state) {
+        return Err(error(
+            ErrorCode::LoginFailed,
+            "unknown or expired sign in state".to_string(),
+        ));
+    }
+
+    let identity = oauth::fetch_identity(&config, &query.code)
+        .await
+        .map_err(|e| {
+            error(
+                ErrorCode::LoginFailed,
+                format!("GitHub sign in failed: {e}"),
+            )
+        })?;
+
+    let min_account_age = config.min_account_age_days * 24 * 60 * 60;
+    if identity.account_age() < min_account_age {
+        return Err(error(
+            ErrorCode::AccountTooNew,
+            format!("GitHub account {} is too new", identity.login),
+        ));
+    }
+
+    info!("{} signed in with GitHub", identity.login);
+    let token = sessions.create_session(identity);
+    let cookie = format!(
+        "{SESSION_COOKIE}={token}; Path=/; Max-Age={SESSION_DURATION}; HttpOnly; Secure; SameSite=Lax"
+    );
+    Ok((
+        [(
+            SET_COOKIE,
+            HeaderValue::from_str(&cookie).expect("valid cookie"),
+        )],
+        Redirect::to("/"),
+    )
+        .into_response())
 }
 
-fn error(error: String, status: StatusCode) -> DispenseError {
-    error!("{}", error);
-    DispenseError { error, status }
+/// Logs the internal details of a failure and turns it into the error returned to the client.
+fn error(code: ErrorCode, details: String) -> DispenseError {
+    error!("{}", details);
+    code.into()
 }
 
 fn available_balance(inputs: &[Input], base_asset_id: &AssetId) -> u64 {
@@ -0,0 +1,68 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::Notify;
+
+/// Keeps track of dispenses that are still being processed, so that the server can stop
+/// accepting new ones on shutdown and wait for the outstanding ones to be committed and tracked.
+#[derive(Debug, Default)]
+pub struct InFlightDispenses {
+    shutting_down: AtomicBool,
+    count: AtomicUsize,
+    idle: Notify,
+    shutdown: Notify,
+}
+
+impl InFlightDispenses {
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        self.shutdown.notify_waiters();
+    }
+
+    /// Resolves once the shutdown begins, so that long-lived responses like event streams can end
+    /// rather than holding up the graceful shutdown.
+    pub async fn shutdown_started(&self) {
+        let shutdown = self.shutdown.notified();
+        tokio::pin!(shutdown);
+        shutdown.as_mut().enable();
+
+        if !self.is_shutting_down() {
+            shutdown.await;
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Registers a new dispense, which is considered in-flight until the returned guard is dropped.
+    pub fn start(self: &Arc<Self>) -> InFlightGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.clone())
+    }
+
+    pub async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+
+            if self.count.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InFlightGuard(Arc<InFlightDispenses>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
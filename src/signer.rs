@@ -0,0 +1,128 @@
+//! Signing of faucet transactions, either with a key held in memory or by an external signing
+//! service so that the key never has to be loaded into the faucet.
+
+use async_trait::async_trait;
+use fuel_crypto::{Message, Signature};
+use fuel_types::Address;
+use fuels_accounts::wallet::WalletUnlocked;
+use fuels_core::{
+    error,
+    traits::Signer,
+    types::{bech32::Bech32Address, errors::Result},
+};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+
+/// How long the signing service has to respond, unless the faucet's timeout is shorter. Signing
+/// holds the wallet, so a hung service mustn't block its dispenses for long.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+pub struct SignRequest {
+    /// The address whose key should sign the message
+    pub address: String,
+    /// The 32 byte message (transaction id) to sign, hex encoded
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignResponse {
+    /// The 64 byte secp256k1 signature, hex encoded
+    pub signature: String,
+}
+
+/// Signs messages by calling `POST {url}/sign` on an external signing service, which can keep
+/// the key in a KMS or HSM.
+#[derive(Clone, Debug)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: Url,
+    token: Option<Secret<String>>,
+    address: Bech32Address,
+}
+
+impl RemoteSigner {
+    pub fn new(
+        url: &str,
+        address: Address,
+        token: Option<Secret<String>>,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT.min(timeout))
+                .build()?,
+            url: Url::parse(url)?.join("sign")?,
+            token,
+            address: address.into(),
+        })
+    }
+
+    async fn request_signature(&self, message: &Message) -> anyhow::Result<Signature> {
+        let request = SignRequest {
+            address: format!("{:#x}", Address::from(&self.address)),
+            message: format!("{message:#x}"),
+        };
+        let mut builder = self.client.post(self.url.clone()).json(&request);
+        if let Some(token) = &self.token {
+            builder = builder.bearer_auth(token.expose_secret());
+        }
+        let response: SignResponse = builder.send().await?.error_for_status()?.json().await?;
+
+        Ok(Signature::from_str(&response.signature)?)
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    async fn sign(&self, message: Message) -> Result<Signature> {
+        let signature = self
+            .request_signature(&message)
+            .await
+            .map_err(|e| error!(Other, "remote signer request failed: {e}"))?;
+
+        // Don't submit transactions that the node would reject, e.g. if the service signed with
+        // the wrong key.
+        let public_key = signature
+            .recover(&message)
+            .map_err(|e| error!(Other, "remote signer returned an invalid signature: {e}"))?;
+        if Address::from(*public_key.hash()) != Address::from(&self.address) {
+            return Err(error!(
+                Other,
+                "remote signer signed with a key other than {}", self.address
+            ));
+        }
+
+        Ok(signature)
+    }
+
+    fn address(&self) -> &Bech32Address {
+        &self.address
+    }
+}
+
+/// The signer used for the faucet wallet.
+#[derive(Clone, Debug)]
+pub enum FaucetSigner {
+    Local(Box<WalletUnlocked>),
+    Remote(RemoteSigner),
+}
+
+#[async_trait]
+impl Signer for FaucetSigner {
+    async fn sign(&self, message: Message) -> Result<Signature> {
+        match self {
+            FaucetSigner::Local(wallet) => wallet.sign(message).await,
+            FaucetSigner::Remote(signer) => signer.sign(message).await,
+        }
+    }
+
+    fn address(&self) -> &Bech32Address {
+        match self {
+            FaucetSigner::Local(wallet) => Signer::address(wallet.as_ref()),
+            FaucetSigner::Remote(signer) => signer.address(),
+        }
+    }
+}
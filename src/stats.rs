@@ -0,0 +1,112 @@
+//! Public statistics of the faucet, aggregated from the dispense history. Computing them looks up
+//! the balance of every wallet, so they are cached for a while.
+
+use crate::{
+    dispense_tracker::Clock,
+    history::DispenseHistory,
+    models::{StatsBucket, StatsResponse},
+    wallet_pool::WalletPool,
+};
+use fuels_accounts::ViewOnlyAccount;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+/// The number of hourly and daily buckets.
+const HOURS: u64 = 24;
+const DAYS: u64 = 30;
+
+#[derive(Debug)]
+pub struct Stats {
+    /// How long the statistics are cached for, in seconds
+    ttl: u64,
+    /// The statistics, along with when they were computed according to the clock
+    cached: Mutex<Option<(u64, StatsResponse)>>,
+    clock: Box<dyn Clock>,
+}
+
+impl Stats {
+    pub fn new(ttl: u64, clock: impl Clock + 'static) -> Self {
+        Self {
+            ttl,
+            cached: Mutex::new(None),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// The cached statistics, recomputed once they're older than the ttl. Concurrent requests
+    /// wait for a single recomputation.
+    pub async fn get(&self, history: &DispenseHistory, wallets: &WalletPool) -> StatsResponse {
+        let mut cached = self.cached.lock().await;
+        let now = self.clock.now();
+        if let Some((_, stats)) = cached
+            .as_ref()
+            .filter(|(computed_at, _)| computed_at + self.ttl > now)
+        {
+            return stats.clone();
+        }
+
+        let mut stats = aggregate(history, unix_now());
+        stats.balance = balance(wallets).await;
+        *cached = Some((now, stats.clone()));
+        stats
+    }
+}
+
+fn aggregate(history: &DispenseHistory, now: u64) -> StatsResponse {
+    let mut hourly = buckets(HOUR, HOURS, now);
+    let mut daily = buckets(DAY, DAYS, now);
+    let (unique_recipients, dispenses, total_dispensed) = history.totals();
+
+    history.for_each_hour(|start, dispenses, amount| {
+        for (buckets, size) in [(&mut hourly, HOUR), (&mut daily, DAY)] {
+            let start = start - start % size;
+            if let Some(bucket) = buckets.iter_mut().find(|bucket| bucket.start == start) {
+                bucket.dispenses += dispenses;
+                bucket.amount += amount;
+            }
+        }
+    });
+
+    StatsResponse {
+        total_dispensed,
+        dispenses,
+        unique_recipients,
+        hourly,
+        daily,
+        balance: None,
+        generated_at: now,
+    }
+}
+
+/// `count` empty buckets of `size` seconds, oldest first, the last of which holds `now`.
+fn buckets(size: u64, count: u64, now: u64) -> Vec<StatsBucket> {
+    let current = now - now % size;
+    (0..count)
+        .rev()
+        .map(|age| StatsBucket {
+            start: current.saturating_sub(age * size),
+            dispenses: 0,
+            amount: 0,
+        })
+        .collect()
+}
+
+/// The combined balance of the wallets, if all of them could be looked up.
+async fn balance(wallets: &WalletPool) -> Option<u64> {
+    let provider = wallets.primary().provider().expect("client provider");
+    let base_asset_id = *provider.consensus_parameters().base_asset_id();
+    let mut total = 0;
+    for pooled in wallets.wallets() {
+        total += pooled.wallet.get_asset_balance(&base_asset_id).await.ok()?;
+    }
+    Some(total)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -0,0 +1,400 @@
+//! Detection of sybil farms, which claim to many fresh addresses and then sweep the funds to a
+//! single wallet. Recipients are followed for a while after their dispense, and once enough of
+//! them forward funds to a common collector, the collector and all of its feeders are denylisted
+//! until the collector stops being fed. Admins can unlist false positives, e.g. an exchange that
+//! many honest recipients pay into.
+
+use crate::models::{SybilCluster, SybilReport};
+use fuel_core_client::client::{
+    pagination::{PageDirection, PaginationRequest},
+    types::TransactionStatus,
+    FuelClient,
+};
+use fuel_tx::{
+    field::{Inputs, Outputs},
+    Input, Output, Receipt, Script, Transaction,
+};
+use fuel_types::{Address, AssetId, ContractId};
+use futures::StreamExt;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+/// How many of the latest transactions of each recipient are inspected.
+const TRANSACTIONS_PER_RECIPIENT: i32 = 20;
+/// How many recipients are looked up per analysis, the ones followed longest ago first.
+const LOOKUPS_PER_ANALYSIS: usize = 500;
+/// How many lookups are sent to the node at once.
+const CONCURRENT_LOOKUPS: usize = 8;
+
+#[derive(Debug, Default)]
+struct Followed {
+    dispensed_at: u64,
+    /// When the funds were last looked up, or 0 if they weren't yet
+    followed_at: u64,
+    /// The coins forwarded to other addresses as of the last lookup
+    forwards: Vec<(Address, u64)>,
+}
+
+#[derive(Debug)]
+struct Cluster {
+    /// The amount forwarded to the collector by each feeder
+    feeders: BTreeMap<Address, u64>,
+    detected_at: u64,
+    /// When the cluster is dropped unless it's still being fed
+    expires_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct Analysis {
+    clusters: BTreeMap<Address, Cluster>,
+    /// The collectors and feeders of the clusters, minus the unlisted addresses
+    denylist: HashSet<Address>,
+    /// Addresses unlisted by an admin, which are never denylisted again
+    unlisted: HashSet<Address>,
+    analyzed_at: Option<u64>,
+}
+
+impl Analysis {
+    fn update_denylist(&mut self) {
+        let denylist = self
+            .clusters
+            .iter()
+            .flat_map(|(collector, cluster)| {
+                std::iter::once(collector).chain(cluster.feeders.keys())
+            })
+            .copied()
+            .filter(|address| !self.unlisted.contains(address))
+            .collect();
+        self.denylist = denylist;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SybilDetector {
+    /// Recent recipients, followed until `tracking_period` after their last dispense
+    recipients: Mutex<HashMap<Address, Followed>>,
+    analysis: Mutex<Analysis>,
+}
+
+impl SybilDetector {
+    /// Follows the address after it was dispensed to.
+    pub fn record(&self, address: Address) {
+        self.recipients
+            .lock()
+            .unwrap()
+            .entry(address)
+            .or_default()
+            .dispensed_at = now();
+    }
+
+    pub fn is_denylisted(&self, address: &Address) -> bool {
+        self.analysis.lock().unwrap().denylist.contains(address)
+    }
+
+    /// Takes the address off the denylist for good, e.g. after a false positive. Unlisting a
+    /// collector also drops its cluster, releasing the feeders that aren't in another one.
+    /// Returns whether the address was denylisted.
+    pub fn unlist(&self, address: Address) -> bool {
+        let mut analysis = self.analysis.lock().unwrap();
+        let denylisted = analysis.denylist.contains(&address);
+        analysis.clusters.remove(&address);
+        analysis.unlisted.insert(address);
+        analysis.update_denylist();
+        denylisted
+    }
+
+    pub fn report(&self) -> SybilReport {
+        let analysis = self.analysis.lock().unwrap();
+        let mut denylisted: Vec<_> = analysis
+            .denylist
+            .iter()
+            .map(|address| format!("{address:#x}"))
+            .collect();
+        denylisted.sort();
+        let mut unlisted: Vec<_> = analysis
+            .unlisted
+            .iter()
+            .map(|address| format!("{address:#x}"))
+            .collect();
+        unlisted.sort();
+
+        SybilReport {
+            clusters: analysis
+                .clusters
+                .iter()
+                .map(|(collector, cluster)| SybilCluster {
+                    collector: format!("{collector:#x}"),
+                    feeders: cluster
+                        .feeders
+                        .keys()
+                        .map(|feeder| format!("{feeder:#x}"))
+                        .collect(),
+                    amount: cluster.feeders.values().sum(),
+                    detected_at: cluster.detected_at,
+                    expires_at: cluster.expires_at,
+                })
+                .collect(),
+            denylisted,
+            unlisted,
+            analyzed_at: analysis.analyzed_at,
+        }
+    }
+
+    /// Follows the funds of the recipients dispensed to within `tracking_period` seconds, at
+    /// most `LOOKUPS_PER_ANALYSIS` of them per call. Addresses in `ignored`, i.e. the faucet's
+    /// own wallets and the configured well-known addresses, are never considered collectors.
+    /// Clusters that aren't fed anymore are dropped `denylist_duration` seconds later.
+    pub async fn analyze(
+        &self,
+        client: &FuelClient,
+        ignored: &HashSet<Address>,
+        min_cluster_size: usize,
+        tracking_period: u64,
+        denylist_duration: u64,
+    ) {
+        let now = now();
+        let batch: Vec<Address> = {
+            let mut recipients = self.recipients.lock().unwrap();
+            recipients.retain(|_, followed| followed.dispensed_at + tracking_period > now);
+            let mut batch: Vec<_> = recipients
+                .iter()
+                .map(|(recipient, followed)| (followed.followed_at, *recipient))
+                .collect();
+            batch.sort_unstable();
+            batch
+                .into_iter()
+                .take(LOOKUPS_PER_ANALYSIS)
+                .map(|(_, recipient)| recipient)
+                .collect()
+        };
+
+        let base_asset_id = match client.chain_info().await {
+            Ok(info) => *info.consensus_parameters.base_asset_id(),
+            Err(e) => {
+                warn!("Failed to get the chain info, skipping the sybil analysis: {e}");
+                return;
+            }
+        };
+        let base_asset_id = &base_asset_id;
+        let lookups: Vec<_> = futures::stream::iter(batch)
+            .map(|recipient| async move {
+                (
+                    recipient,
+                    forwarded_funds(client, recipient, base_asset_id).await,
+                )
+            })
+            .buffer_unordered(CONCURRENT_LOOKUPS)
+            .collect()
+            .await;
+
+        // collector -> feeder -> forwarded amount, as of the last lookup of each recipient
+        let mut forwards: HashMap<Address, HashMap<Address, u64>> = HashMap::new();
+        {
+            let mut recipients = self.recipients.lock().unwrap();
+            for (recipient, lookup) in lookups {
+                let Some(followed) = recipients.get_mut(&recipient) else {
+                    continue;
+                };
+                followed.followed_at = now;
+                match lookup {
+                    Ok(outputs) => followed.forwards = outputs,
+                    Err(e) => warn!("Failed to follow the funds of {recipient:#x}: {e}"),
+                }
+            }
+            for (recipient, followed) in recipients.iter() {
+                for (to, amount) in &followed.forwards {
+                    if !ignored.contains(to) {
+                        *forwards
+                            .entry(*to)
+                            .or_default()
+                            .entry(*recipient)
+                            .or_default() += amount;
+                    }
+                }
+            }
+        }
+
+        let mut analysis = self.analysis.lock().unwrap();
+        analysis
+            .clusters
+            .retain(|_, cluster| cluster.expires_at > now);
+        for (collector, mut feeders) in forwards {
+            if analysis.unlisted.contains(&collector) {
+                continue;
+            }
+            feeders.retain(|feeder, _| !analysis.unlisted.contains(feeder));
+            // new feeders of a known collector are denylisted whatever their number
+            let known = analysis.clusters.contains_key(&collector);
+            if !known && feeders.len() < min_cluster_size {
+                continue;
+            }
+            if !known {
+                warn!(
+                    "Detected {} recipients forwarding funds to {collector:#x}, denylisting them",
+                    feeders.len()
+                );
+            }
+
+            let cluster = analysis
+                .clusters
+                .entry(collector)
+                .or_insert_with(|| Cluster {
+                    feeders: BTreeMap::new(),
+                    detected_at: now,
+                    expires_at: now,
+                });
+            cluster.expires_at = now + denylist_duration;
+            cluster.feeders.extend(feeders);
+        }
+        analysis.update_denylist();
+        analysis.analyzed_at = Some(now);
+    }
+}
+
+/// Runs the analysis every `interval` seconds.
+pub async fn run(
+    detector: Arc<SybilDetector>,
+    client: FuelClient,
+    ignored: HashSet<Address>,
+    min_cluster_size: usize,
+    tracking_period: u64,
+    denylist_duration: u64,
+    interval: u64,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        interval.tick().await;
+        detector
+            .analyze(
+                &client,
+                &ignored,
+                min_cluster_size,
+                tracking_period,
+                denylist_duration,
+            )
+            .await;
+        info!(
+            "Sybil analysis complete, {} addresses denylisted",
+            detector.analysis.lock().unwrap().denylist.len()
+        );
+    }
+}
+
+/// The coins that the recipient sent to other addresses in its latest successful transactions,
+/// including the change of the ones sweeping its coins to another owner.
+async fn forwarded_funds(
+    client: &FuelClient,
+    recipient: Address,
+    base_asset_id: &AssetId,
+) -> anyhow::Result<Vec<(Address, u64)>> {
+    let transactions = client
+        .transactions_by_owner(
+            &recipient,
+            PaginationRequest {
+                cursor: None,
+                results: TRANSACTIONS_PER_RECIPIENT,
+                direction: PageDirection::Backward,
+            },
+        )
+        .await?;
+
+    Ok(transactions
+        .results
+        .into_iter()
+        .filter_map(|tx| match (tx.transaction, tx.status) {
+            (
+                Transaction::Script(script),
+                TransactionStatus::Success {
+                    receipts,
+                    total_fee,
+                    ..
+                },
+            ) => Some((script, receipts, total_fee)),
+            _ => None,
+        })
+        // only transactions spending the recipient's coins, not the ones funding it
+        .filter(|(script, _, _)| {
+            script
+                .inputs()
+                .iter()
+                .any(|input| input.input_owner() == Some(&recipient))
+        })
+        .flat_map(|(script, receipts, total_fee)| {
+            script
+                .outputs()
+                .iter()
+                .filter_map(|output| match output {
+                    Output::Coin { to, amount, .. } if *to != recipient => Some((*to, *amount)),
+                    Output::Change { to, asset_id, .. } if *to != recipient => {
+                        let fee = if asset_id == base_asset_id {
+                            total_fee
+                        } else {
+                            0
+                        };
+                        Some((
+                            *to,
+                            change(&script, &receipts, asset_id, base_asset_id, fee),
+                        ))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// The amount of the asset that the change output received once the transaction executed: what
+/// the inputs held beyond the coin outputs, the fee and what the script sent to contracts.
+fn change(
+    script: &Script,
+    receipts: &[Receipt],
+    asset_id: &AssetId,
+    base_asset_id: &AssetId,
+    fee: u64,
+) -> u64 {
+    let inputs: u64 = script
+        .inputs()
+        .iter()
+        .filter(|input| input.asset_id(base_asset_id) == Some(asset_id))
+        .filter_map(Input::amount)
+        .sum();
+    let outputs: u64 = script
+        .outputs()
+        .iter()
+        .filter_map(|output| match output {
+            Output::Coin {
+                amount,
+                asset_id: coin_asset_id,
+                ..
+            } if coin_asset_id == asset_id => Some(*amount),
+            _ => None,
+        })
+        .sum();
+    // the script's own transfers, as opposed to the ones made by the contracts it calls
+    let transferred: u64 = receipts
+        .iter()
+        .filter(|receipt| {
+            matches!(
+                receipt,
+                Receipt::Call { .. } | Receipt::Transfer { .. } | Receipt::TransferOut { .. }
+            ) && receipt.id() == Some(&ContractId::zeroed())
+                && receipt.asset_id() == Some(asset_id)
+        })
+        .filter_map(Receipt::amount)
+        .sum();
+    inputs
+        .saturating_sub(outputs)
+        .saturating_sub(fee)
+        .saturating_sub(transferred)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
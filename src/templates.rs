@@ -0,0 +1,76 @@
+//! The pages served by the faucet. Each template can be overridden by a file of the same name in
+//! the template directory, and otherwise is the minified copy embedded at build time. Templates
+//! can use the configured template variables, e.g. `{{ network_name }}`.
+
+use anyhow::Context;
+use handlebars::Handlebars;
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, fs, path::Path};
+use tracing::info;
+
+pub const INDEX: &str = "index.html";
+pub const STATS: &str = "stats.html";
+pub const EMBED: &str = "embed.html";
+
+const EMBEDDED: [(&str, &str); 3] = [
+    (INDEX, include_str!(concat!(env!("OUT_DIR"), "/index.html"))),
+    (STATS, include_str!(concat!(env!("OUT_DIR"), "/stats.html"))),
+    (EMBED, include_str!(concat!(env!("OUT_DIR"), "/embed.html"))),
+];
+
+/// Variables the embedded templates use, unless they're configured.
+const DEFAULT_VARS: [(&str, &str); 2] = [
+    ("page_title", "Fuel Faucet"),
+    ("network_name", "Test Fuel network"),
+];
+
+#[derive(Debug)]
+pub struct Templates {
+    handlebars: Handlebars<'static>,
+    vars: Map<String, Value>,
+}
+
+impl Templates {
+    pub fn load(
+        template_dir: Option<&Path>,
+        vars: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let mut handlebars = Handlebars::new();
+        for (name, embedded) in EMBEDDED {
+            let path = template_dir
+                .map(|dir| dir.join(name))
+                .filter(|path| path.exists());
+            let template = match &path {
+                Some(path) => {
+                    info!("Using the template {path:?}");
+                    fs::read_to_string(path).with_context(|| format!("unable to read {path:?}"))?
+                }
+                None => embedded.to_string(),
+            };
+            handlebars
+                .register_template_string(name, template)
+                .with_context(|| format!("invalid template {name}"))?;
+        }
+
+        let vars = DEFAULT_VARS
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .chain(vars.clone())
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect();
+        Ok(Self { handlebars, vars })
+    }
+
+    pub fn vars(&self) -> &Map<String, Value> {
+        &self.vars
+    }
+
+    /// Renders the template with the template variables, overridden by the fields of `data`.
+    pub fn render(&self, name: &str, data: Value) -> anyhow::Result<String> {
+        let mut context = self.vars.clone();
+        if let Value::Object(data) = data {
+            context.extend(data);
+        }
+        Ok(self.handlebars.render(name, &context)?)
+    }
+}
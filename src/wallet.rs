@@ -0,0 +1,194 @@
+//! The faucet wallet, and maintenance operations on it used by the `wallet` subcommands.
+
+use crate::signer::FaucetSigner;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use fuel_tx::{Output, TxId};
+use fuel_types::{Address, AssetId};
+use fuels_accounts::{
+    impersonated_account::ImpersonatedAccount, provider::Provider, Account, ViewOnlyAccount,
+};
+use fuels_core::types::{
+    bech32::Bech32Address,
+    coin::{Coin, CoinStatus},
+    coin_type::CoinType,
+    coin_type_id::CoinTypeId,
+    errors::Result,
+    input::Input,
+    transaction::{Transaction, TxPolicies},
+    transaction_builders::{BuildableTransaction, ScriptTransactionBuilder, TransactionBuilder},
+};
+
+/// An account whose transactions are signed by a [`FaucetSigner`], so the key may live outside
+/// of the faucet.
+#[derive(Clone, Debug)]
+pub struct FaucetWallet {
+    account: ImpersonatedAccount,
+    signer: FaucetSigner,
+}
+
+impl FaucetWallet {
+    pub fn new(signer: FaucetSigner, provider: Option<Provider>) -> Self {
+        use fuels_core::traits::Signer;
+        Self {
+            account: ImpersonatedAccount::new(signer.address().clone(), provider),
+            signer,
+        }
+    }
+
+    pub fn address(&self) -> &Bech32Address {
+        self.account.address()
+    }
+
+    pub fn provider(&self) -> Option<&Provider> {
+        self.account.try_provider().ok()
+    }
+}
+
+impl ViewOnlyAccount for FaucetWallet {
+    fn address(&self) -> &Bech32Address {
+        self.account.address()
+    }
+
+    fn try_provider(&self) -> Result<&Provider> {
+        self.account.try_provider()
+    }
+}
+
+#[async_trait]
+impl Account for FaucetWallet {
+    async fn get_asset_inputs_for_amount(
+        &self,
+        asset_id: AssetId,
+        amount: u64,
+        excluded_coins: Option<Vec<CoinTypeId>>,
+    ) -> Result<Vec<Input>> {
+        self.account
+            .get_asset_inputs_for_amount(asset_id, amount, excluded_coins)
+            .await
+    }
+
+    fn add_witnesses<Tb: TransactionBuilder>(&self, tb: &mut Tb) -> Result<()> {
+        tb.add_signer(self.signer.clone())?;
+
+        Ok(())
+    }
+}
+
+/// Returns the unspent base asset coins owned by the wallet, largest first.
+pub async fn spendable_coins(wallet: &FaucetWallet) -> anyhow::Result<Vec<Coin>> {
+    let base_asset_id = *wallet.try_provider()?.base_asset_id();
+    let mut coins: Vec<_> = wallet
+        .get_coins(base_asset_id)
+        .await?
+        .into_iter()
+        .filter(|coin| coin.status == CoinStatus::Unspent)
+        .collect();
+    coins.sort_by_key(|coin| std::cmp::Reverse(coin.amount));
+    Ok(coins)
+}
+
+/// Creates `count` coins of `amount` each, so that the faucet has enough coins to build
+/// concurrent dispenses from.
+pub async fn split(wallet: &FaucetWallet, count: u16, amount: u64) -> anyhow::Result<TxId> {
+    let provider = wallet.try_provider()?;
+    let base_asset_id = *provider.base_asset_id();
+    let max_outputs = provider.consensus_parameters().tx_params().max_outputs();
+    if count >= max_outputs {
+        return Err(anyhow!(
+            "can't create more than {} coins in a transaction",
+            max_outputs - 1
+        ));
+    }
+
+    let total = amount
+        .checked_mul(count as u64)
+        .ok_or_else(|| anyhow!("total amount overflows"))?;
+    let inputs = wallet
+        .get_asset_inputs_for_amount(base_asset_id, total, None)
+        .await?;
+    let owner: Address = wallet.address().into();
+    let outputs = std::iter::repeat_n(Output::coin(owner, amount, base_asset_id), count as usize)
+        .chain([Output::change(owner, 0, base_asset_id)])
+        .collect();
+
+    submit(wallet, inputs, outputs, total).await
+}
+
+/// Merges the smallest coins together until at most `target` coins remain. Each transaction
+/// spends as many coins as a transaction allows.
+pub async fn consolidate(wallet: &FaucetWallet, target: usize) -> anyhow::Result<Vec<TxId>> {
+    let provider = wallet.try_provider()?;
+    let base_asset_id = *provider.base_asset_id();
+    // leave room for an extra input in case the merged coins don't cover the fee
+    let max_inputs = provider.consensus_parameters().tx_params().max_inputs() as usize - 1;
+    let owner: Address = wallet.address().into();
+    let target = target.max(1);
+
+    let mut tx_ids = vec![];
+    loop {
+        let coins = spendable_coins(wallet).await?;
+        if coins.len() <= target {
+            return Ok(tx_ids);
+        }
+
+        let batch_size = max_inputs.min(coins.len() - target + 1);
+        let inputs = coins
+            .into_iter()
+            .rev()
+            .take(batch_size)
+            .map(|coin| Input::resource_signed(CoinType::Coin(coin)))
+            .collect();
+        let outputs = vec![Output::change(owner, 0, base_asset_id)];
+
+        tx_ids.push(submit(wallet, inputs, outputs, 0).await?);
+    }
+}
+
+/// Transfers every base asset coin of the wallet to `recipient`.
+pub async fn sweep(wallet: &FaucetWallet, recipient: Address) -> anyhow::Result<Vec<TxId>> {
+    let provider = wallet.try_provider()?;
+    let base_asset_id = *provider.base_asset_id();
+    let max_inputs = provider.consensus_parameters().tx_params().max_inputs() as usize;
+
+    let mut tx_ids = vec![];
+    loop {
+        let coins = spendable_coins(wallet).await?;
+        if coins.is_empty() {
+            return Ok(tx_ids);
+        }
+
+        let inputs = coins
+            .into_iter()
+            .take(max_inputs)
+            .map(|coin| Input::resource_signed(CoinType::Coin(coin)))
+            .collect();
+        let outputs = vec![Output::change(recipient, 0, base_asset_id)];
+
+        tx_ids.push(submit(wallet, inputs, outputs, 0).await?);
+    }
+}
+
+async fn submit(
+    wallet: &FaucetWallet,
+    inputs: Vec<Input>,
+    outputs: Vec<Output>,
+    used_base_amount: u64,
+) -> anyhow::Result<TxId> {
+    let provider = wallet.try_provider()?;
+    let mut tx_builder =
+        ScriptTransactionBuilder::prepare_transfer(inputs, outputs, TxPolicies::default());
+    wallet.add_witnesses(&mut tx_builder)?;
+    wallet
+        .adjust_for_fee(&mut tx_builder, used_base_amount)
+        .await?;
+
+    let tx = tx_builder.build(provider).await?;
+    let tx_id = tx.id(provider.chain_id());
+    provider
+        .send_transaction_and_await_commit(tx)
+        .await?
+        .check(None)?;
+
+    Ok(tx_id)
+}
@@ -0,0 +1,103 @@
+use crate::{wallet::FaucetWallet, FaucetState};
+use fuels_core::types::node_info::NodeInfo;
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// A faucet wallet together with its own chain of coin outputs and tips, so that wallets build
+/// and submit transactions independently of each other.
+#[derive(Debug)]
+pub struct PooledWallet {
+    pub wallet: FaucetWallet,
+    pub state: tokio::sync::Mutex<FaucetState>,
+    busy: AtomicUsize,
+    drained: AtomicBool,
+}
+
+impl PooledWallet {
+    /// Whether the last attempt to dispense from this wallet failed for lack of funds.
+    pub fn is_drained(&self) -> bool {
+        self.drained.load(Ordering::SeqCst)
+    }
+
+    pub fn set_drained(&self, drained: bool) {
+        self.drained.store(drained, Ordering::SeqCst);
+    }
+}
+
+/// The wallets the faucet dispenses from. Dispenses go to the least busy wallet, in round-robin
+/// order between equally busy ones, and drained wallets are only used when all of them are.
+#[derive(Debug)]
+pub struct WalletPool {
+    wallets: Vec<Arc<PooledWallet>>,
+    next: AtomicUsize,
+}
+
+impl WalletPool {
+    pub fn new(wallets: Vec<FaucetWallet>, node_info: &NodeInfo) -> Self {
+        assert!(!wallets.is_empty(), "At least one wallet is required");
+        Self {
+            wallets: wallets
+                .into_iter()
+                .map(|wallet| {
+                    Arc::new(PooledWallet {
+                        wallet,
+                        state: tokio::sync::Mutex::new(FaucetState::new(node_info)),
+                        busy: AtomicUsize::new(0),
+                        drained: AtomicBool::new(false),
+                    })
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The first configured wallet, used for queries that don't depend on a specific wallet.
+    pub fn primary(&self) -> &FaucetWallet {
+        &self.wallets[0].wallet
+    }
+
+    pub fn wallets(&self) -> impl Iterator<Item = &PooledWallet> {
+        self.wallets.iter().map(Arc::as_ref)
+    }
+
+    pub fn len(&self) -> usize {
+        self.wallets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wallets.is_empty()
+    }
+
+    /// Picks the wallet to dispense from, which counts as busy until the lease is dropped.
+    pub fn acquire(&self) -> WalletLease {
+        let start = self.next.fetch_add(1, Ordering::SeqCst);
+        let wallet = (0..self.wallets.len())
+            .map(|i| &self.wallets[(start + i) % self.wallets.len()])
+            .min_by_key(|wallet| (wallet.is_drained(), wallet.busy.load(Ordering::SeqCst)))
+            .expect("The pool isn't empty");
+        wallet.busy.fetch_add(1, Ordering::SeqCst);
+        WalletLease(wallet.clone())
+    }
+}
+
+#[derive(Debug)]
+pub struct WalletLease(Arc<PooledWallet>);
+
+impl Deref for WalletLease {
+    type Target = PooledWallet;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for WalletLease {
+    fn drop(&mut self) {
+        self.0.busy.fetch_sub(1, Ordering::SeqCst);
+    }
+}
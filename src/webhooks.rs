@@ -0,0 +1,314 @@
+//! Webhooks notifying partners of dispenses and low balances. Each payload is signed with
+//! HMAC-SHA256 over `{timestamp}.{body}`, sent in the `X-Faucet-Signature` header as
+//! `sha256=<hex>` alongside the timestamp in `X-Faucet-Timestamp`. Deliveries that keep failing
+//! are retried with exponential backoff, then appended to the dead-letter file. So are the ones
+//! still retrying when the faucet stops.
+
+use crate::{
+    config::Config,
+    models::{FaucetEvent, WebhookEvent, WebhookPayload},
+};
+use fuel_types::Bytes32;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+use sha2::Sha256;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    task::Poll,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    task::JoinSet,
+};
+use tracing::{error, warn};
+
+const SIGNATURE_HEADER: &str = "X-Faucet-Signature";
+const TIMESTAMP_HEADER: &str = "X-Faucet-Timestamp";
+const DELIVERY_HEADER: &str = "X-Faucet-Delivery";
+/// The delay before the first retry, doubled for each of the next ones.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A delivery that failed every attempt.
+#[derive(Debug, Serialize)]
+struct DeadLetter<'a> {
+    url: &'a str,
+    attempts: u32,
+    error: String,
+    failed_at: u64,
+    payload: &'a WebhookPayload,
+}
+
+/// A delivery that hasn't succeeded nor been dead-lettered yet.
+#[derive(Debug)]
+struct Pending {
+    index: usize,
+    payload: Arc<WebhookPayload>,
+    attempts: u32,
+    error: Option<anyhow::Error>,
+}
+
+#[derive(Debug, Default)]
+struct Deliveries {
+    tasks: JoinSet<()>,
+    pending: HashMap<u64, Pending>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+pub struct Webhooks {
+    urls: Vec<String>,
+    secret: Secret<String>,
+    max_attempts: u32,
+    dead_letter_file: Option<PathBuf>,
+    /// Serializes the writes to the dead-letter file
+    dead_letters: Mutex<()>,
+    low_balance_threshold: Option<u64>,
+    client: reqwest::Client,
+    deliveries: Mutex<Deliveries>,
+}
+
+impl Webhooks {
+    /// The configured webhooks, if any. They can't be configured without a secret.
+    pub fn new(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.webhook_urls.is_empty() {
+            return Ok(None);
+        }
+        let secret = config
+            .webhook_secret
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("webhooks need a secret to sign their payloads"))?;
+
+        Ok(Some(Self {
+            urls: config.webhook_urls.clone(),
+            secret,
+            max_attempts: config.webhook_max_attempts.max(1),
+            dead_letter_file: config.webhook_dead_letter_file.clone(),
+            dead_letters: Mutex::new(()),
+            low_balance_threshold: config.low_balance_threshold,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            deliveries: Mutex::default(),
+        }))
+    }
+
+    /// Delivers the payload to every webhook, each in its own task so that a slow endpoint
+    /// doesn't hold up the others.
+    fn notify(self: &Arc<Self>, event: WebhookEvent) {
+        let payload = Arc::new(WebhookPayload {
+            id: format!("{:x}", Bytes32::from(rand::thread_rng().gen::<[u8; 32]>())),
+            created_at: now(),
+            event,
+        });
+        let mut deliveries = self.deliveries.lock().unwrap();
+        // finished tasks are only dropped once joined
+        while deliveries.tasks.try_join_next().is_some() {}
+        for index in 0..self.urls.len() {
+            let id = deliveries.next_id;
+            deliveries.next_id += 1;
+            deliveries.pending.insert(
+                id,
+                Pending {
+                    index,
+                    payload: payload.clone(),
+                    attempts: 0,
+                    error: None,
+                },
+            );
+            let (webhooks, payload) = (self.clone(), payload.clone());
+            deliveries
+                .tasks
+                .spawn(async move { webhooks.deliver(id, index, &payload).await });
+        }
+    }
+
+    /// Waits for the deliveries in flight to succeed or be dead-lettered, retries included.
+    pub async fn flush(&self) {
+        std::future::poll_fn(|cx| {
+            let mut deliveries = self.deliveries.lock().unwrap();
+            while let Poll::Ready(Some(_)) = deliveries.tasks.poll_join_next(cx) {}
+            if deliveries.tasks.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Stops the deliveries in flight and dead-letters them, e.g. when the faucet can't wait for
+    /// their retries any longer.
+    pub async fn abandon(&self) {
+        let mut tasks = std::mem::take(&mut self.deliveries.lock().unwrap().tasks);
+        tasks.abort_all();
+        while tasks.join_next().await.is_some() {}
+        let pending = std::mem::take(&mut self.deliveries.lock().unwrap().pending);
+        for pending in pending.into_values() {
+            let error = match pending.error {
+                Some(error) => error.context("the faucet stopped before the next attempt"),
+                None => anyhow::anyhow!("the faucet stopped during the first attempt"),
+            };
+            self.dead_letter(
+                &self.urls[pending.index],
+                pending.attempts,
+                error,
+                &pending.payload,
+            );
+        }
+    }
+
+    async fn deliver(&self, id: u64, index: usize, payload: &WebhookPayload) {
+        let url = &self.urls[index];
+        let body = serde_json::to_string(payload).expect("payloads are serializable");
+        let mut delay = RETRY_DELAY;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match self.send(url, &payload.id, &body).await {
+                Ok(()) => break,
+                Err(e) => e,
+            };
+            if attempts >= self.max_attempts {
+                self.dead_letter(url, attempts, error, payload);
+                break;
+            }
+            warn!(
+                "Failed to deliver webhook {} to {url}: {error:#}",
+                payload.id
+            );
+            // kept for the dead letter, should the faucet stop before the next attempt
+            if let Some(pending) = self.deliveries.lock().unwrap().pending.get_mut(&id) {
+                pending.attempts = attempts;
+                pending.error = Some(error);
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+        }
+        self.deliveries.lock().unwrap().pending.remove(&id);
+    }
+
+    async fn send(&self, url: &str, id: &str, body: &str) -> anyhow::Result<()> {
+        let timestamp = now().to_string();
+        self.client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, self.sign(&timestamp, body))
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(DELIVERY_HEADER, id)
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn sign(&self, timestamp: &str, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.expose_secret().as_bytes())
+            .expect("HMAC takes keys of any size");
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn dead_letter(
+        &self,
+        url: &str,
+        attempts: u32,
+        error: anyhow::Error,
+        payload: &WebhookPayload,
+    ) {
+        error!(
+            "Giving up on webhook {} to {url} after {attempts} attempts: {error:#}",
+            payload.id
+        );
+        let Some(path) = &self.dead_letter_file else {
+            return;
+        };
+        let letter = DeadLetter {
+            url,
+            attempts,
+            error: format!("{error:#}"),
+            failed_at: now(),
+            payload,
+        };
+        let line = serde_json::to_string(&letter).expect("dead letters are serializable");
+
+        let _guard = self.dead_letters.lock().unwrap();
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{line}"));
+        if let Err(e) = written {
+            error!("Failed to write the dead letter to {path:?}: {e}");
+        }
+    }
+}
+
+/// Notifies the webhooks of the faucet's events until the event channel closes.
+pub async fn run(webhooks: Arc<Webhooks>, mut events: Receiver<FaucetEvent>) {
+    // wallets below the threshold, so that each is reported once until it's topped up
+    let mut low_wallets = HashSet::new();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => {
+                warn!("Webhooks missed {missed} events");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        match event {
+            FaucetEvent::DispenseCommitted {
+                recipient,
+                tx_id,
+                amount,
+            } => webhooks.notify(WebhookEvent::DispenseCommitted {
+                recipient,
+                tx_id,
+                amount,
+            }),
+            FaucetEvent::DispenseFailed {
+                recipient,
+                amount,
+                code,
+            } => webhooks.notify(WebhookEvent::DispenseFailed {
+                recipient,
+                amount,
+                code,
+            }),
+            FaucetEvent::BalanceChanged { wallet, balance } => {
+                let Some(threshold) = webhooks.low_balance_threshold else {
+                    continue;
+                };
+                if balance >= threshold {
+                    low_wallets.remove(&wallet);
+                } else if low_wallets.insert(wallet.clone()) {
+                    webhooks.notify(WebhookEvent::LowBalance {
+                        wallet,
+                        balance,
+                        threshold,
+                    });
+                }
+            }
+            FaucetEvent::DispenseSubmitted { .. } => {}
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -1,29 +1,46 @@
+use axum::{
+    http::HeaderMap,
+    routing::{get, post},
+    Form, Json, Router,
+};
 use fuel_core::chain_config::{
-    ChainConfig, CoinConfig, CoinConfigGenerator, SnapshotReader, StateConfig,
+    ChainConfig, CoinConfig, CoinConfigGenerator, ContractConfig, SnapshotReader, StateConfig,
 };
 use fuel_core::service::config::Trigger;
 use fuel_core::service::{Config as NodeConfig, FuelService};
 
 use fuel_core_client::client::pagination::{PageDirection, PaginationRequest};
-use fuel_crypto::SecretKey;
+use fuel_crypto::{Message, PublicKey, SecretKey, Signature};
 use fuel_faucet::config::Config;
-use fuel_faucet::models::DispenseInfoResponse;
-use fuel_faucet::{start_server, Clock};
+use fuel_faucet::models::{
+    DispenseHistoryResponse, DispenseInfoResponse, DispenseInput, DispenseStatus, ErrorCode,
+    OwnershipProof, RateLimitMode, RecipientType, SybilReport, WebhookEvent, WebhookPayload,
+};
+use fuel_faucet::{
+    setup_wallet, signer::FaucetSigner, start_server_with_shutdown, wallet, wallet::FaucetWallet,
+    Clock,
+};
+use fuel_faucet_client::{FaucetClient, FaucetClientError};
 use fuel_tx::ConsensusParameters;
-use fuel_types::Address;
+use fuel_types::{Address, Bytes32, ContractId, Salt};
 use fuels_accounts::provider::Provider;
 use fuels_accounts::wallet::WalletUnlocked;
-use fuels_core::types::bech32::Bech32Address;
-use fuels_core::types::transaction::TransactionType;
+use fuels_accounts::Account;
+use fuels_core::types::bech32::{Bech32Address, Bech32ContractId};
+use fuels_core::types::transaction::{TransactionType, TxPolicies};
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
+use hmac::{Hmac, Mac};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
-use secrecy::Secret;
-use serde_json::json;
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::{sync::oneshot, task::JoinHandle};
 
 #[derive(Debug, Clone)]
 struct MockClock {
@@ -54,10 +71,18 @@ struct TestContext {
     faucet_config: Config,
     provider: Provider,
     addr: SocketAddr,
+    contract_id: ContractId,
+    faucet: FaucetClient,
     clock: MockClock,
+    shutdown: Option<oneshot::Sender<()>>,
+    server: JoinHandle<anyhow::Result<()>>,
 }
 impl TestContext {
     async fn new(rng: &mut StdRng) -> Self {
+        Self::new_with_config(rng, |config| config).await
+    }
+
+    async fn new_with_config(rng: &mut StdRng, configure: impl FnOnce(Config) -> Config) -> Self {
         let dispense_amount = 2000000;
         let secret_key: SecretKey = SecretKey::random(rng);
         let wallet = WalletUnlocked::new_from_private_key(secret_key, None);
@@ -73,8 +98,17 @@ impl TestContext {
             })
             .collect();
 
+        // a deployed contract to dispense to
+        let mut contract = ContractConfig {
+            code: vec![0; 8],
+            ..Default::default()
+        };
+        contract.update_contract_id(Salt::zeroed());
+        let contract_id = contract.contract_id;
+
         let state_config = StateConfig {
             coins,
+            contracts: vec![contract],
             ..Default::default()
         };
 
@@ -113,24 +147,35 @@ impl TestContext {
             .unwrap();
 
         // start faucet
-        let faucet_config = Config {
+        let faucet_config = configure(Config {
             service_port: 0,
             node_url: format!("http://{}", fuel_node.bound_address),
             wallet_secret_key: Some(Secret::new(format!("{secret_key:x}"))),
             dispense_amount,
+            max_dispense_amount: dispense_amount,
+            dispense_budget: dispense_amount,
             number_of_retries: 1,
             ..Default::default()
-        };
+        });
 
         let clock = MockClock::new();
-        let (addr, _) = start_server(faucet_config.clone(), clock.clone()).await;
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let (addr, server) =
+            start_server_with_shutdown(faucet_config.clone(), clock.clone(), async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
 
         Self {
             fuel_node,
             faucet_config,
             provider,
             addr,
+            contract_id,
+            faucet: FaucetClient::new(&format!("http://{addr}")).unwrap(),
             clock,
+            shutdown: Some(shutdown),
+            server,
         }
     }
 }
@@ -138,6 +183,30 @@ impl TestContext {
 #[tokio::test]
 async fn can_start_server() {
     let context = TestContext::new(&mut StdRng::seed_from_u64(42)).await;
+
+    let response = context.faucet.info().await.expect("Invalid response body");
+
+    assert_eq!(response.amount, context.faucet_config.dispense_amount);
+    assert_eq!(
+        response.asset_id,
+        context
+            .provider
+            .consensus_parameters()
+            .base_asset_id()
+            .to_string()
+    );
+
+    let health = context
+        .faucet
+        .health()
+        .await
+        .expect("Invalid response body");
+    assert!(health.fuel_core);
+}
+
+#[tokio::test]
+async fn serves_unversioned_aliases_and_openapi_spec() {
+    let context = TestContext::new(&mut StdRng::seed_from_u64(42)).await;
     let addr = context.addr;
 
     let client = reqwest::Client::new();
@@ -150,16 +219,17 @@ async fn can_start_server() {
         .json::<DispenseInfoResponse>()
         .await
         .expect("Invalid response body");
-
     assert_eq!(response.amount, context.faucet_config.dispense_amount);
-    assert_eq!(
-        response.asset_id,
-        context
-            .provider
-            .consensus_parameters()
-            .base_asset_id()
-            .to_string()
-    );
+
+    let spec = client
+        .get(format!("http://{addr}/openapi.json"))
+        .send()
+        .await
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .expect("Invalid response body");
+    assert!(spec["paths"]["/v1/dispense"]["post"].is_object());
 }
 
 #[tokio::test]
@@ -194,18 +264,12 @@ async fn _dispense_sends_coins_to_valid_address(
     recipient_address_str: String,
 ) {
     let context = TestContext::new(&mut rng).await;
-    let addr = context.addr;
-    let client = reqwest::Client::new();
 
-    client
-        .post(format!("http://{addr}/dispense"))
-        .json(&json!({
-            "captcha": "",
-            "address": recipient_address_str,
-        }))
-        .send()
+    context
+        .faucet
+        .dispense(&recipient_address_str, "")
         .await
-        .unwrap();
+        .expect("Dispense should succeed");
 
     let test_balance: u64 = context
         .provider
@@ -222,6 +286,726 @@ async fn _dispense_sends_coins_to_valid_address(
     assert!(test_balance >= context.faucet_config.dispense_amount);
 }
 
+#[tokio::test]
+async fn dispense_rejects_invalid_address() {
+    let context = TestContext::new(&mut StdRng::seed_from_u64(42)).await;
+
+    let error = context
+        .faucet
+        .dispense("not an address", "")
+        .await
+        .expect_err("Invalid address should be rejected");
+
+    match error {
+        FaucetClientError::Api {
+            status,
+            code,
+            error,
+        } => {
+            assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+            assert_eq!(code, ErrorCode::InvalidAddress);
+            assert_eq!(error, "invalid address");
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
+
+#[tokio::test]
+async fn reads_unknown_error_codes() {
+    // a faucet newer than the client, failing with a code the client doesn't know of
+    let respond = || async {
+        (
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "code": "SOME_NEW_CODE", "error": "something new" })),
+        )
+    };
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener).unwrap().serve(
+            Router::new()
+                .route("/v1/dispense", get(respond))
+                .into_make_service(),
+        ),
+    );
+
+    let error = FaucetClient::new(&format!("http://{addr}"))
+        .unwrap()
+        .info()
+        .await
+        .expect_err("The request should fail");
+    match error {
+        FaucetClientError::Api {
+            status,
+            code,
+            error,
+        } => {
+            assert_eq!(status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(code, ErrorCode::Unknown);
+            assert_eq!(error, "something new");
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
+
+#[tokio::test]
+async fn api_key_replaces_captcha() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let recipient_address_str = format!("{}", &recipient_address);
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        captcha_secret: Some(Secret::new("captcha secret".to_string())),
+        api_keys: vec![Secret::new("api key".to_string())],
+        ..config
+    })
+    .await;
+
+    let error = context
+        .faucet
+        .clone()
+        .with_api_key("wrong key")
+        .dispense(&recipient_address_str, "")
+        .await
+        .expect_err("Invalid API key should fall back to the captcha");
+    assert_eq!(error.code(), Some(ErrorCode::CaptchaFailed));
+
+    context
+        .faucet
+        .clone()
+        .with_api_key("api key")
+        .dispense(&recipient_address_str, "")
+        .await
+        .expect("Valid API key should skip the captcha");
+}
+
+#[tokio::test]
+async fn loads_wallet_from_encrypted_keystore() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let context = TestContext::new_with_config(&mut rng, |config| {
+        let secret_key: SecretKey = config
+            .wallet_secret_key
+            .as_ref()
+            .unwrap()
+            .expose_secret()
+            .parse()
+            .unwrap();
+        let keystore_dir = std::env::temp_dir().join("faucet-test-keystore");
+        std::fs::create_dir_all(&keystore_dir).unwrap();
+        let keystore = WalletUnlocked::new_from_private_key(secret_key, None)
+            .encrypt(&keystore_dir, "password")
+            .unwrap();
+
+        Config {
+            wallet_secret_key: None,
+            wallet_keystore: Some(keystore_dir.join(keystore)),
+            wallet_keystore_password: Some(Secret::new("password".to_string())),
+            ..config
+        }
+    })
+    .await;
+
+    context
+        .faucet
+        .dispense(&format!("{recipient_address:#x}"), "")
+        .await
+        .expect("Dispense from the keystore wallet should succeed");
+}
+
+#[tokio::test]
+async fn applies_the_policy_of_the_client_network() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address = format!("{:#x}", rng.gen::<Address>());
+    // unique to the run, so that concurrent runs don't rewrite each other's policies
+    let policy_dir = std::env::temp_dir().join(format!(
+        "faucet-test-ip-policies-{}-{}",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&policy_dir).unwrap();
+    let policy_file = policy_dir.join("policies.txt");
+    std::fs::write(&policy_file, "127.0.0.0/8 block\n").unwrap();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        api_keys: vec![Secret::new("key".to_string())],
+        ip_policy_file: Some(policy_file.clone()),
+        ip_policy_reload_interval: 1,
+        ..config
+    })
+    .await;
+
+    let error = context
+        .faucet
+        .dispense(&recipient_address, "")
+        .await
+        .expect_err("Requests from blocked networks should be rejected");
+    assert_eq!(error.code(), Some(ErrorCode::NetworkBlocked));
+    context
+        .faucet
+        .clone()
+        .with_api_key("key")
+        .dispense(&format!("{:#x}", rng.gen::<Address>()), "")
+        .await
+        .expect("API key holders should be exempt from the policies");
+
+    // the most specific range wins, and changes apply without a restart
+    std::fs::write(
+        &policy_file,
+        "127.0.0.0/8 block\n127.0.0.1   reduce 1000 # this host\n",
+    )
+    .unwrap();
+    let response = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            match context.faucet.dispense(&recipient_address, "").await {
+                Ok(response) => break response,
+                Err(error) => assert_eq!(error.code(), Some(ErrorCode::NetworkBlocked)),
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("The policies should be reloaded");
+    assert_eq!(response.tokens, 1000);
+    let _ = std::fs::remove_dir_all(&policy_dir);
+}
+
+#[tokio::test]
+async fn falls_back_to_funded_wallets() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_addresses = generate_recipient_addresses(3, &mut rng);
+    // a second wallet without any coins
+    let empty_key = SecretKey::random(&mut rng);
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        wallet_secret_keys: vec![Secret::new(format!("{empty_key:x}"))],
+        ..config
+    })
+    .await;
+
+    for address in &recipient_addresses {
+        context
+            .faucet
+            .dispense(address, "")
+            .await
+            .expect("Dispense should fall back to the funded wallet");
+    }
+
+    let health = context.faucet.health().await.unwrap();
+    assert_eq!(health.wallets.len(), 2);
+    assert!(health.wallets[0].balance.unwrap() > 0);
+    assert_eq!(health.wallets[1].balance, Some(0));
+}
+
+/// Serves the GitHub OAuth endpoints. The code sent to the callback is exchanged for an access
+/// token naming the user, where `old` has an old account and any other user a new one.
+fn start_mock_github() -> SocketAddr {
+    let token = |Form(params): Form<HashMap<String, String>>| async move {
+        Json(serde_json::json!({ "access_token": params["code"] }))
+    };
+    let user = |headers: HeaderMap| async move {
+        let login = headers["authorization"]
+            .to_str()
+            .unwrap()
+            .strip_prefix("Bearer ")
+            .unwrap()
+            .to_string();
+        let created_at = if login == "old" {
+            "2015-01-01T00:00:00Z".to_string()
+        } else {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            chrono::DateTime::from_timestamp(now.as_secs() as i64, 0)
+                .unwrap()
+                .to_rfc3339()
+        };
+        Json(serde_json::json!({ "id": login.len(), "login": login, "created_at": created_at }))
+    };
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener).unwrap().serve(
+            Router::new()
+                .route("/login/oauth/access_token", post(token))
+                .route("/user", get(user))
+                .into_make_service(),
+        ),
+    );
+    addr
+}
+
+/// Signs in through the faucet's GitHub flow, returning the session token.
+async fn sign_in_with_github(addr: SocketAddr, user: &str) -> Result<String, ErrorCode> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("http://{addr}/v1/auth/github"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SEE_OTHER);
+    let authorize_url =
+        reqwest::Url::parse(response.headers()["location"].to_str().unwrap()).unwrap();
+    let state = authorize_url
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .unwrap()
+        .1
+        .to_string();
+
+    let response = client
+        .get(format!("http://{addr}/v1/auth/github/callback"))
+        .query(&[("code", user), ("state", &state)])
+        .send()
+        .await
+        .unwrap();
+    if response.status() != reqwest::StatusCode::SEE_OTHER {
+        let error: serde_json::Value = response.json().await.unwrap();
+        return Err(serde_json::from_value(error["code"].clone()).unwrap());
+    }
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    let token = cookie
+        .split(';')
+        .next()
+        .unwrap()
+        .strip_prefix("faucet_session=")
+        .unwrap();
+    Ok(token.to_string())
+}
+
+#[tokio::test]
+async fn github_sign_in_raises_the_budget() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_addresses = generate_recipient_addresses(3, &mut rng);
+    let github = start_mock_github();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        github_client_id: Some("client id".to_string()),
+        github_client_secret: Some(Secret::new("client secret".to_string())),
+        github_authorize_url: format!("http://{github}/login/oauth/authorize"),
+        github_token_url: format!("http://{github}/login/oauth/access_token"),
+        github_user_url: format!("http://{github}/user"),
+        identity_dispense_budget: config.dispense_budget * 2,
+        ..config
+    })
+    .await;
+
+    assert_eq!(
+        sign_in_with_github(context.addr, "new").await,
+        Err(ErrorCode::AccountTooNew)
+    );
+
+    let session = sign_in_with_github(context.addr, "old").await.unwrap();
+    let faucet = context.faucet.clone().with_session(session);
+    faucet
+        .dispense(&recipient_addresses[0], "")
+        .await
+        .expect("Signed in users should receive assets");
+    // the budget is accounted against the account, whichever the recipient
+    faucet
+        .dispense(&recipient_addresses[1], "")
+        .await
+        .expect("Signed in users should have a larger budget");
+    let error = faucet
+        .dispense(&recipient_addresses[2], "")
+        .await
+        .expect_err("The account's budget should be used up");
+    assert_eq!(error.code(), Some(ErrorCode::RateLimited));
+
+    // the login state can't be replayed
+    let response = reqwest::get(format!(
+        "http://{}/v1/auth/github/callback?code=old&state=unknown",
+        context.addr
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+}
+
+/// Serves `POST /sign` like an external signing service holding `secret_key`, or failing every
+/// request without one.
+fn start_mock_signer(secret_key: Option<SecretKey>, requests: Arc<AtomicUsize>) -> SocketAddr {
+    let sign = move |Json(request): Json<serde_json::Value>| async move {
+        requests.fetch_add(1, Ordering::SeqCst);
+        let Some(secret_key) = secret_key else {
+            return Err(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        };
+        let message: Bytes32 = request["message"].as_str().unwrap().parse().unwrap();
+        let signature = Signature::sign(&secret_key, &Message::from_bytes(*message));
+        Ok(Json(
+            serde_json::json!({ "signature": format!("{signature:x}") }),
+        ))
+    };
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(Router::new().route("/sign", post(sign)).into_make_service()),
+    );
+    addr
+}
+
+#[tokio::test]
+async fn dispenses_with_remote_signer() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let context = TestContext::new_with_config(&mut rng, |config| {
+        let secret_key: SecretKey = config
+            .wallet_secret_key
+            .as_ref()
+            .unwrap()
+            .expose_secret()
+            .parse()
+            .unwrap();
+        let address = WalletUnlocked::new_from_private_key(secret_key, None)
+            .address()
+            .to_string();
+        let signer = start_mock_signer(Some(secret_key), requests.clone());
+
+        // the key is only known to the signer
+        Config {
+            wallet_secret_key: None,
+            remote_signer_url: Some(format!("http://{signer}")),
+            remote_signer_address: Some(address),
+            ..config
+        }
+    })
+    .await;
+
+    context
+        .faucet
+        .dispense(&format!("{recipient_address:#x}"), "")
+        .await
+        .expect("Dispense signed by the remote signer should succeed");
+    assert!(requests.load(Ordering::SeqCst) > 0);
+}
+
+#[tokio::test]
+async fn reports_remote_signer_failures() {
+    // the signer is down, or signs with a key other than the faucet wallet's
+    for signing_key in [None, Some(SecretKey::random(&mut StdRng::seed_from_u64(7)))] {
+        let mut rng = StdRng::seed_from_u64(42);
+        let recipient_address = format!("{:#x}", rng.gen::<Address>());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let context = TestContext::new_with_config(&mut rng, |config| {
+            let secret_key: SecretKey = config
+                .wallet_secret_key
+                .as_ref()
+                .unwrap()
+                .expose_secret()
+                .parse()
+                .unwrap();
+            let address = WalletUnlocked::new_from_private_key(secret_key, None)
+                .address()
+                .to_string();
+            let signer = start_mock_signer(signing_key, requests.clone());
+            Config {
+                wallet_secret_key: None,
+                remote_signer_url: Some(format!("http://{signer}")),
+                remote_signer_address: Some(address),
+                ..config
+            }
+        })
+        .await;
+
+        // the faucet keeps reporting the failure rather than losing the wallet
+        for _ in 0..2 {
+            let error = context
+                .faucet
+                .dispense(&recipient_address, "")
+                .await
+                .expect_err("Dispense without a valid signature should fail");
+            assert_eq!(error.code(), Some(ErrorCode::InternalError));
+        }
+        assert!(requests.load(Ordering::SeqCst) >= 2);
+    }
+}
+
+async fn prove_ownership(
+    faucet: &FaucetClient,
+    address: &str,
+    secret_key: &SecretKey,
+) -> OwnershipProof {
+    let nonce = faucet.nonce(address).await.unwrap().nonce;
+    let signature = Signature::sign(secret_key, &Message::new(&nonce));
+    OwnershipProof {
+        nonce,
+        signature: format!("{signature:x}"),
+    }
+}
+
+#[tokio::test]
+async fn ownership_proof_raises_the_budget() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let secret_key = SecretKey::random(&mut rng);
+    let address = format!("{:#x}", Address::from(*PublicKey::from(&secret_key).hash()));
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        require_ownership_proof: true,
+        verified_dispense_budget: config.dispense_budget * 2,
+        ..config
+    })
+    .await;
+    let input = |proof| DispenseInput {
+        address: address.clone(),
+        captcha: String::new(),
+        recipient_type: RecipientType::Address,
+        amount: None,
+        proof,
+        redirect_url: None,
+    };
+
+    let error = context
+        .faucet
+        .dispense(&address, "")
+        .await
+        .expect_err("Dispenses without a proof should be rejected");
+    assert_eq!(error.code(), Some(ErrorCode::ProofRequired));
+
+    // a nonce signed by another key doesn't prove anything
+    let other_key = SecretKey::random(&mut rng);
+    let proof = prove_ownership(&context.faucet, &address, &other_key).await;
+    let error = context
+        .faucet
+        .dispense_with(&input(Some(proof)))
+        .await
+        .expect_err("Proofs signed by another key should be rejected");
+    assert_eq!(error.code(), Some(ErrorCode::InvalidProof));
+
+    // nonces are bound to the address they were issued for
+    let other_address = format!("{:#x}", rng.gen::<Address>());
+    let proof = prove_ownership(&context.faucet, &other_address, &secret_key).await;
+    let error = context
+        .faucet
+        .dispense_with(&input(Some(proof)))
+        .await
+        .expect_err("Nonces of other addresses shouldn't be redeemable");
+    assert_eq!(error.code(), Some(ErrorCode::InvalidProof));
+    // and can't be extended
+    let mut proof = prove_ownership(&context.faucet, &address, &secret_key).await;
+    let (expires_at, rest) = proof.nonce.split_once('.').unwrap();
+    proof.nonce = format!("{}.{rest}", expires_at.parse::<u64>().unwrap() + 60);
+    proof.signature = format!(
+        "{:x}",
+        Signature::sign(&secret_key, &Message::new(&proof.nonce))
+    );
+    let error = context
+        .faucet
+        .dispense_with(&input(Some(proof)))
+        .await
+        .expect_err("Forged nonces shouldn't be redeemable");
+    assert_eq!(error.code(), Some(ErrorCode::InvalidProof));
+
+    let proof = prove_ownership(&context.faucet, &address, &secret_key).await;
+    context
+        .faucet
+        .dispense_with(&input(Some(proof.clone())))
+        .await
+        .expect("Verified owners should receive assets");
+    // nonces can only be used once
+    let error = context
+        .faucet
+        .dispense_with(&input(Some(proof)))
+        .await
+        .expect_err("Nonces shouldn't be reusable");
+    assert_eq!(error.code(), Some(ErrorCode::InvalidProof));
+
+    let proof = prove_ownership(&context.faucet, &address, &secret_key).await;
+    context
+        .faucet
+        .dispense_with(&input(Some(proof)))
+        .await
+        .expect("Verified owners should have a larger budget");
+}
+
+#[tokio::test]
+async fn denylists_recipients_forwarding_to_a_collector() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let feeders: Vec<SecretKey> = (0..3).map(|_| SecretKey::random(&mut rng)).collect();
+    let collector: Address = rng.gen();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        api_keys: vec![Secret::new("admin".to_string())],
+        sybil_detection: true,
+        sybil_analysis_interval: 1,
+        sybil_min_cluster_size: feeders.len(),
+        ..config
+    })
+    .await;
+    sweep_to_collector(&context, &feeders, collector).await;
+
+    let admin = context.faucet.clone().with_api_key("admin");
+    let report = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let report = admin.sybil_report().await.unwrap();
+            if !report.clusters.is_empty() {
+                break report;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("The collector should be detected");
+    assert_eq!(report.clusters[0].collector, format!("{collector:#x}"));
+    assert_eq!(report.clusters[0].feeders.len(), feeders.len());
+    assert_eq!(report.denylisted.len(), feeders.len() + 1);
+
+    let error = context
+        .faucet
+        .dispense(&format!("{collector:#x}"), "")
+        .await
+        .expect_err("The collector should be denylisted");
+    assert_eq!(error.code(), Some(ErrorCode::Denylisted));
+
+    // a false positive is unlisted along with its feeders, and isn't detected again
+    let report = admin
+        .sybil_unlist(&format!("{collector:#x}"))
+        .await
+        .unwrap();
+    assert!(report.clusters.is_empty());
+    assert!(report.denylisted.is_empty());
+    assert_eq!(report.unlisted, vec![format!("{collector:#x}")]);
+    let unlisted_at = report.analyzed_at.unwrap();
+    let report = wait_for_analysis_after(&admin, unlisted_at).await;
+    assert!(report.clusters.is_empty());
+    context
+        .faucet
+        .dispense(&format!("{collector:#x}"), "")
+        .await
+        .expect("The collector should be unlisted");
+
+    let error = context
+        .faucet
+        .sybil_report()
+        .await
+        .expect_err("The report should require an API key");
+    assert_eq!(error.code(), Some(ErrorCode::ApiKeyRequired));
+    let error = context
+        .faucet
+        .sybil_unlist(&format!("{collector:#x}"))
+        .await
+        .expect_err("Unlisting should require an API key");
+    assert_eq!(error.code(), Some(ErrorCode::ApiKeyRequired));
+}
+
+#[tokio::test]
+async fn detects_sweeps_through_change_outputs() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let feeders: Vec<SecretKey> = (0..3).map(|_| SecretKey::random(&mut rng)).collect();
+    let collector: Address = rng.gen();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        api_keys: vec![Secret::new("admin".to_string())],
+        sybil_detection: true,
+        sybil_analysis_interval: 1,
+        sybil_min_cluster_size: feeders.len(),
+        ..config
+    })
+    .await;
+
+    // each recipient sweeps all of its funds to the collector as change
+    for secret_key in &feeders {
+        let wallet = FaucetWallet::new(
+            FaucetSigner::Local(Box::new(WalletUnlocked::new_from_private_key(
+                *secret_key,
+                None,
+            ))),
+            Some(context.provider.clone()),
+        );
+        context
+            .faucet
+            .dispense(&format!("{:#x}", Address::from(wallet.address())), "")
+            .await
+            .unwrap();
+        wallet::sweep(&wallet, collector).await.unwrap();
+    }
+
+    let admin = context.faucet.clone().with_api_key("admin");
+    let report = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let report = admin.sybil_report().await.unwrap();
+            if !report.clusters.is_empty() {
+                break report;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("The collector should be detected");
+    assert_eq!(report.clusters[0].collector, format!("{collector:#x}"));
+    assert_eq!(report.clusters[0].feeders.len(), feeders.len());
+    // the dispensed amounts, less the fees of the sweeps
+    let dispensed = context.faucet_config.dispense_amount * feeders.len() as u64;
+    assert!(report.clusters[0].amount > 0 && report.clusters[0].amount < dispensed);
+}
+
+#[tokio::test]
+async fn never_denylists_well_known_addresses() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let feeders: Vec<SecretKey> = (0..3).map(|_| SecretKey::random(&mut rng)).collect();
+    let exchange: Address = rng.gen();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        api_keys: vec![Secret::new("admin".to_string())],
+        sybil_detection: true,
+        sybil_analysis_interval: 1,
+        sybil_min_cluster_size: feeders.len(),
+        sybil_ignored_addresses: vec![format!("{exchange:#x}")],
+        ..config
+    })
+    .await;
+
+    sweep_to_collector(&context, &feeders, exchange).await;
+
+    let admin = context.faucet.clone().with_api_key("admin");
+    let swept_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let report = wait_for_analysis_after(&admin, swept_at).await;
+    assert!(report.clusters.is_empty());
+    assert!(report.denylisted.is_empty());
+}
+
+/// Dispenses to each feeder, which then sweeps half of its funds to the collector.
+async fn sweep_to_collector(context: &TestContext, feeders: &[SecretKey], collector: Address) {
+    let base_asset_id = *context.provider.consensus_parameters().base_asset_id();
+    for secret_key in feeders {
+        let wallet =
+            WalletUnlocked::new_from_private_key(*secret_key, Some(context.provider.clone()));
+        context
+            .faucet
+            .dispense(&format!("{:#x}", Address::from(wallet.address())), "")
+            .await
+            .unwrap();
+        wallet
+            .transfer(
+                &Bech32Address::from(collector),
+                context.faucet_config.dispense_amount / 2,
+                base_asset_id,
+                TxPolicies::default(),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+async fn wait_for_analysis_after(admin: &FaucetClient, after: u64) -> SybilReport {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let report = admin.sybil_report().await.unwrap();
+            if report
+                .analyzed_at
+                .is_some_and(|analyzed_at| analyzed_at > after)
+            {
+                break report;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("The analysis should run")
+}
+
 fn generate_recipient_addresses(count: usize, rng: &mut StdRng) -> Vec<String> {
     let recipient_addresses: Vec<Address> =
         std::iter::repeat_with(|| rng.gen()).take(count).collect();
@@ -238,33 +1022,16 @@ async fn many_concurrent_requests() {
     const COUNT: usize = 128;
     let recipient_addresses_str = generate_recipient_addresses(COUNT, &mut rng);
     let context = TestContext::new(&mut rng).await;
-    let addr = context.addr;
 
     let mut queries = vec![];
     for recipient in recipient_addresses_str {
-        let recipient = recipient.clone();
-        queries.push(async move {
-            let client = reqwest::Client::new();
-            client
-                .post(format!("http://{addr}/dispense"))
-                .json(&json!({
-                    "captcha": "",
-                    "address": recipient,
-                }))
-                .send()
-                .await
-        });
+        let faucet = context.faucet.clone();
+        queries.push(async move { faucet.dispense(&recipient, "").await });
     }
     let mut queries = FuturesUnordered::from_iter(queries);
     let mut success = 0;
     while let Some(query) = queries.next().await {
-        let response = query.expect("Query should be successful");
-        assert_eq!(
-            response.status(),
-            reqwest::StatusCode::CREATED,
-            "{success}/{COUNT}: {:?}",
-            response.bytes().await
-        );
+        query.unwrap_or_else(|e| panic!("{success}/{COUNT}: {e}"));
         success += 1;
     }
 
@@ -286,54 +1053,867 @@ async fn many_concurrent_requests() {
 }
 
 #[tokio::test]
-async fn dispense_once_per_day() {
-    let mut rng = StdRng::seed_from_u64(42);
-    let recipient_address: Address = rng.gen();
-    let recipient_address_str = format!("{}", &recipient_address);
-    let context = TestContext::new(&mut rng).await;
-    let addr = context.addr;
+async fn dispense_to_contract() {
+    let context = TestContext::new(&mut StdRng::seed_from_u64(42)).await;
+    let contract_id = format!("{:#x}", context.contract_id);
 
-    let dispense_interval = 24 * 60 * 60;
-    let time_increment = dispense_interval / 6;
+    context
+        .faucet
+        .dispense_to_contract(&contract_id, "")
+        .await
+        .expect("Dispense to the contract should succeed");
 
-    let response = reqwest::Client::new()
-        .post(format!("http://{addr}/dispense"))
-        .json(&json!({
-            "captcha": "",
-            "address": recipient_address_str.clone(),
-        }))
-        .send()
+    let base_asset_id = *context.provider.consensus_parameters().base_asset_id();
+    let balance = context
+        .provider
+        .get_contract_asset_balance(&Bech32ContractId::from(context.contract_id), base_asset_id)
         .await
-        .expect("First dispensing request should be successful");
+        .unwrap();
+    assert_eq!(balance, context.faucet_config.dispense_amount);
 
-    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    // contracts are rate limited separately from addresses
+    let error = context
+        .faucet
+        .dispense_to_contract(&contract_id, "")
+        .await
+        .expect_err("Contract should be rate limited");
+    assert_eq!(error.code(), Some(ErrorCode::RateLimited));
+    context
+        .faucet
+        .dispense(&contract_id, "")
+        .await
+        .expect("The same id as an address should not be rate limited");
+}
 
-    for _ in 0..5 {
-        context.clock.advance(time_increment);
+#[tokio::test]
+async fn requested_amounts_share_a_budget() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        max_dispense_amount: config.dispense_amount * 2,
+        dispense_budget: config.dispense_amount * 3,
+        ..config
+    })
+    .await;
+    let dispense_amount = context.faucet_config.dispense_amount;
+    let request = |amount| DispenseInput {
+        address: format!("{recipient_address:#x}"),
+        captcha: String::new(),
+        recipient_type: RecipientType::Address,
+        amount: Some(amount),
+        proof: None,
+        redirect_url: None,
+    };
+
+    let error = context
+        .faucet
+        .dispense_with(&request(dispense_amount * 2 + 1))
+        .await
+        .expect_err("Amounts above the max should be rejected");
+    assert_eq!(error.code(), Some(ErrorCode::InvalidAmount));
+
+    let response = context
+        .faucet
+        .dispense_with(&request(dispense_amount / 2))
+        .await
+        .expect("Small amount should be dispensed");
+    assert_eq!(response.tokens, dispense_amount / 2);
+    context
+        .faucet
+        .dispense_with(&request(dispense_amount * 2))
+        .await
+        .expect("Max amount should be dispensed within the budget");
+
+    let eligibility = context
+        .faucet
+        .eligibility(&format!("{recipient_address:#x}"))
+        .await
+        .unwrap();
+    assert!(!eligibility.eligible);
+    assert_eq!(eligibility.code, Some(ErrorCode::RateLimited));
+    assert_eq!(eligibility.remaining, dispense_amount / 2);
+
+    let error = context
+        .faucet
+        .dispense_with(&request(dispense_amount))
+        .await
+        .expect_err("Requests beyond the budget should be rate limited");
+    assert_eq!(error.code(), Some(ErrorCode::RateLimited));
+
+    context
+        .clock
+        .advance(context.faucet_config.dispense_limit_interval + 1);
+    context
+        .faucet
+        .dispense_with(&request(dispense_amount))
+        .await
+        .expect("The budget should be restored after the interval");
+}
+
+#[tokio::test]
+async fn tightens_limits_while_traffic_is_high() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_addresses = generate_recipient_addresses(4, &mut rng);
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        adaptive_rate_limiting: true,
+        adaptive_baseline: 2,
+        adaptive_cooldown: 600,
+        ..config
+    })
+    .await;
+    let dispense_amount = context.faucet_config.dispense_amount;
+
+    for address in &recipient_addresses[..3] {
+        context.faucet.dispense(address, "").await.unwrap();
+    }
+    let health = context.faucet.health().await.unwrap();
+    assert_eq!(health.rate_limit_mode, RateLimitMode::Elevated);
+    let info = context.faucet.info().await.unwrap();
+    assert_eq!(info.amount, dispense_amount / 2);
+
+    let response = context
+        .faucet
+        .dispense(&recipient_addresses[3], "")
+        .await
+        .unwrap();
+    assert_eq!(response.tokens, dispense_amount / 2);
+
+    let metrics = reqwest::get(format!("http://{}/metrics", context.addr))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains("faucet_rate_limit_mode{mode=\"elevated\"} 1"));
+    assert!(metrics.contains("faucet_dispense_requests_total{outcome=\"dispensed\"} 4"));
+
+    // the limits relax once traffic has been calm for the cooldown
+    context.clock.advance(60 + 600);
+    let health = context.faucet.health().await.unwrap();
+    assert_eq!(health.rate_limit_mode, RateLimitMode::Normal);
+}
+
+#[tokio::test]
+async fn streams_dispense_events() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let context = TestContext::new(&mut rng).await;
 
-        let response = reqwest::Client::new()
-            .post(format!("http://{addr}/dispense"))
-            .json(&json!({
-                "captcha": "",
-                "address": recipient_address_str.clone(),
-            }))
+    let mut events = reqwest::get(format!("http://{}/v1/events", context.addr))
+        .await
+        .unwrap();
+    let response = context
+        .faucet
+        .dispense(&format!("{recipient_address:#x}"), "")
+        .await
+        .unwrap();
+
+    let mut stream = String::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !stream.contains("event: balance_changed") {
+            let chunk = events.chunk().await.unwrap().expect("The stream ended");
+            stream.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    })
+    .await
+    .expect("The events should be streamed");
+
+    let truncated = format!("{recipient_address:#x}");
+    let truncated = format!("{}…{}", &truncated[..6], &truncated[truncated.len() - 4..]);
+    for name in ["dispense_submitted", "dispense_committed"] {
+        let data = stream
+            .split("\n\n")
+            .find(|event| event.contains(&format!("event: {name}")))
+            .and_then(|event| event.lines().find_map(|line| line.strip_prefix("data: ")))
+            .unwrap_or_else(|| panic!("missing {name} event"));
+        let event: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(event["recipient"], truncated.as_str());
+        assert_eq!(event["tx_id"], response.tx_id.as_str());
+        assert_eq!(event["amount"], response.tokens);
+    }
+}
+
+#[tokio::test]
+async fn links_the_explorer_and_reports_the_new_balance() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address = format!("{:#x}", rng.gen::<Address>());
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        explorer_tx_url: Some("https://explorer.example.com/tx/{tx_id}".to_string()),
+        ..config
+    })
+    .await;
+
+    let first = context
+        .faucet
+        .dispense(&recipient_address, "")
+        .await
+        .unwrap();
+    assert_eq!(
+        first.explorer_url,
+        Some(format!("https://explorer.example.com/tx/0x{}", first.tx_id))
+    );
+    assert_eq!(first.balance, Some(first.tokens));
+
+    context.clock.advance(24 * 60 * 60);
+    let second = context
+        .faucet
+        .dispense(&recipient_address, "")
+        .await
+        .unwrap();
+    assert_eq!(second.balance, Some(first.tokens + second.tokens));
+}
+
+#[tokio::test]
+async fn lists_the_dispense_history() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address = format!("{:#x}", rng.gen::<Address>());
+    let history_file = std::env::temp_dir().join("faucet-test-dispense-history.jsonl");
+    let _ = std::fs::remove_file(&history_file);
+    let mut context = TestContext::new_with_config(&mut rng, |config| Config {
+        dispense_history_file: Some(history_file.clone()),
+        ..config
+    })
+    .await;
+
+    let empty = context
+        .faucet
+        .history(&recipient_address, None)
+        .await
+        .unwrap();
+    assert!(empty.records.is_empty());
+    assert_eq!(empty.next_cursor, None);
+
+    let mut tx_ids = vec![];
+    for _ in 0..3 {
+        let response = context
+            .faucet
+            .dispense(&recipient_address, "")
+            .await
+            .unwrap();
+        tx_ids.push(response.tx_id);
+        context.clock.advance(24 * 60 * 60);
+    }
+
+    // newest first, two at a time
+    let client = reqwest::Client::new();
+    let url = format!(
+        "http://{}/v1/dispense/history/{recipient_address}",
+        context.addr
+    );
+    let page: DispenseHistoryResponse = client
+        .get(&url)
+        .query(&[("limit", 2)])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        page.records
+            .iter()
+            .map(|record| record.tx_id.clone().unwrap())
+            .collect::<Vec<_>>(),
+        vec![tx_ids[2].clone(), tx_ids[1].clone()]
+    );
+    assert!(page
+        .records
+        .iter()
+        .all(|record| record.status == DispenseStatus::Committed && record.amount > 0));
+    let cursor = page.next_cursor.expect("There should be another page");
+
+    let last = context
+        .faucet
+        .history(&recipient_address, Some(cursor))
+        .await
+        .unwrap();
+    assert_eq!(last.records.len(), 1);
+    assert_eq!(last.records[0].tx_id.as_ref(), Some(&tx_ids[0]));
+    assert_eq!(last.next_cursor, None);
+
+    // and kept in the file, to be loaded back after a restart, once the writer is done
+    context.shutdown.take().unwrap().send(()).unwrap();
+    context.server.await.unwrap().unwrap();
+    let persisted = std::fs::read_to_string(&history_file).unwrap();
+    assert_eq!(persisted.lines().count(), 3);
+    assert!(persisted.contains(&recipient_address));
+}
+
+#[tokio::test]
+async fn reads_evicted_history_from_the_file() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address = format!("{:#x}", rng.gen::<Address>());
+    let history_file = std::env::temp_dir().join("faucet-test-evicted-history.jsonl");
+    // more records than are kept for the recipient, then enough recipients to evict it
+    let line = |recipient: &str, amount: u64| {
+        format!(
+            r#"{{"recipient":"{recipient}","recipient_type":"address","tx_id":null,"amount":{amount},"timestamp":1,"status":"committed"}}"#
+        )
+    };
+    let mut lines: Vec<_> = (0..1200)
+        .map(|amount| line(&recipient_address, amount))
+        .collect();
+    lines.extend((0..10_001).map(|_| line(&format!("{:#x}", rng.gen::<Address>()), 1)));
+    std::fs::write(&history_file, lines.join("\n") + "\n").unwrap();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        dispense_history_file: Some(history_file.clone()),
+        ..config
+    })
+    .await;
+
+    let mut amounts = vec![];
+    let mut cursor = None;
+    loop {
+        let page = context
+            .faucet
+            .history(&recipient_address, cursor)
+            .await
+            .unwrap();
+        amounts.extend(page.records.iter().map(|record| record.amount));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(amounts, (0..1200).rev().collect::<Vec<_>>());
+
+    let unknown = context
+        .faucet
+        .history(&format!("{:#x}", rng.gen::<Address>()), None)
+        .await
+        .unwrap();
+    assert!(unknown.records.is_empty());
+}
+
+#[tokio::test]
+async fn aggregates_the_stats() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let context = TestContext::new(&mut rng).await;
+
+    let empty = context.faucet.stats().await.unwrap();
+    assert_eq!(empty.dispenses, 0);
+    assert_eq!(empty.hourly.len(), 24);
+    assert_eq!(empty.daily.len(), 30);
+
+    let recipient_address = format!("{:#x}", rng.gen::<Address>());
+    let mut amounts = 0;
+    for recipient in [
+        recipient_address.clone(),
+        format!("{:#x}", rng.gen::<Address>()),
+    ] {
+        amounts += context
+            .faucet
+            .dispense(&recipient, "")
+            .await
+            .unwrap()
+            .tokens;
+    }
+    context.clock.advance(24 * 60 * 60);
+    amounts += context
+        .faucet
+        .dispense(&recipient_address, "")
+        .await
+        .unwrap()
+        .tokens;
+
+    // cached until the clock moves past the ttl
+    assert_eq!(context.faucet.stats().await.unwrap().dispenses, 0);
+    context.clock.advance(60);
+    let stats = context.faucet.stats().await.unwrap();
+    assert_eq!(stats.dispenses, 3);
+    assert_eq!(stats.unique_recipients, 2);
+    assert_eq!(stats.total_dispensed, amounts);
+    assert_eq!(
+        stats
+            .hourly
+            .iter()
+            .map(|bucket| bucket.dispenses)
+            .sum::<u64>(),
+        3
+    );
+    assert_eq!(
+        stats.daily.iter().map(|bucket| bucket.amount).sum::<u64>(),
+        amounts
+    );
+    assert!(stats.balance.is_some());
+
+    let page = reqwest::Client::new()
+        .get(format!("http://{}/stats", context.addr))
+        .header("Accept", "text/html")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(page.contains("Faucet Statistics"));
+    assert!(page.contains(&amounts.to_string()));
+}
+
+#[tokio::test]
+async fn renders_the_configured_templates() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let template_dir = std::env::temp_dir().join("faucet-test-templates");
+    std::fs::create_dir_all(&template_dir).unwrap();
+    std::fs::write(
+        template_dir.join("index.html"),
+        "<h1>{{ page_title }} on {{ network_name }}</h1>",
+    )
+    .unwrap();
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        template_dir: Some(template_dir.clone()),
+        template_vars: [
+            ("page_title".to_string(), "Devnet Faucet".to_string()),
+            ("network_name".to_string(), "Devnet".to_string()),
+        ]
+        .into(),
+        ..config
+    })
+    .await;
+
+    let index = reqwest::get(format!("http://{}/", context.addr))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(index, "<h1>Devnet Faucet on Devnet</h1>");
+
+    // templates missing from the directory fall back to the embedded ones
+    let stats = reqwest::Client::new()
+        .get(format!("http://{}/stats", context.addr))
+        .header("Accept", "text/html")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(stats.contains("<title>Devnet Faucet Statistics</title>"));
+}
+
+#[tokio::test]
+async fn translates_the_pages_and_errors() {
+    let context = TestContext::new(&mut StdRng::seed_from_u64(42)).await;
+    let client = reqwest::Client::new();
+
+    let page = |lang: &str, accept_language: &str| {
+        client
+            .get(format!("http://{}/?lang={lang}", context.addr))
+            .header("Accept-Language", accept_language)
             .send()
+    };
+    // the query parameter takes precedence over the header
+    let spanish = page("es", "fr").await.unwrap().text().await.unwrap();
+    assert!(spanish.contains(r#"<html lang="es">"#));
+    assert!(spanish.contains("Dame Test Ether"));
+    // then the best supported language of the header
+    let french = page("", "xx, de;q=0.5, fr-CA;q=0.8").await.unwrap();
+    assert_eq!(french.headers()["vary"], "accept-language");
+    assert!(french
+        .text()
+        .await
+        .unwrap()
+        .contains("Recevoir du Test Ether"));
+    let english = page("", "xx").await.unwrap().text().await.unwrap();
+    assert!(english.contains("Give me Test Ether"));
+    // the parameter is decoded, and only read under its own name
+    let portuguese = page("pt%2DBR", "").await.unwrap().text().await.unwrap();
+    assert!(portuguese.contains(r#"<html lang="pt">"#));
+    let english = client
+        .get(format!("http://{}/?xlang=fr", context.addr))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(english.contains(r#"<html lang="en">"#));
+
+    let error = client
+        .post(format!("http://{}/dispense", context.addr))
+        .header("Accept-Language", "es-ES")
+        .json(&serde_json::json!({ "address": "not an address", "captcha": "" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error.status(), reqwest::StatusCode::BAD_REQUEST);
+    let error: serde_json::Value = error.json().await.unwrap();
+    assert_eq!(error["code"], "INVALID_ADDRESS");
+    assert_eq!(error["error"], "Dirección no válida");
+}
+
+#[tokio::test]
+async fn serves_the_embeddable_widget() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        embed_allowed_origins: vec![
+            "https://wallet.example.com".to_string(),
+            "http://localhost:5173".to_string(),
+        ],
+        ..config
+    })
+    .await;
+
+    let response = reqwest::get(format!("http://{}/embed?lang=fr", context.addr))
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()["content-security-policy"],
+        "frame-ancestors 'self' https://wallet.example.com http://localhost:5173"
+    );
+    let page = response.text().await.unwrap();
+    assert!(page.contains("postMessage"));
+    assert!(
+        page.contains(r#"data-parent-origins="https://wallet.example.com http://localhost:5173""#)
+    );
+    assert!(page.contains("Recevoir du Test Ether"));
+}
+
+#[tokio::test]
+async fn only_redirects_to_allowed_origins() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address = format!("{:#x}", rng.gen::<Address>());
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        redirect_allowed_origins: vec!["https://wallet.example.com".to_string()],
+        ..config
+    })
+    .await;
+
+    let page = |redirect_url: &str| {
+        reqwest::get(format!(
+            "http://{}/?redirectUrl={redirect_url}",
+            context.addr
+        ))
+    };
+    let allowed = page("https%3A%2F%2Fwallet.example.com%2Fdone")
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(allowed.contains("https://wallet.example.com/done"));
+    let phishing = page("https%3A%2F%2Fwallet.example.com.evil.com%2Fdone")
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(!phishing.contains("evil.com"));
+
+    let dispense = |redirect_url: &str| {
+        let input = DispenseInput {
+            address: recipient_address.clone(),
+            captcha: String::new(),
+            recipient_type: RecipientType::Address,
+            amount: Some(1),
+            proof: None,
+            redirect_url: Some(redirect_url.to_string()),
+        };
+        let faucet = &context.faucet;
+        async move { faucet.dispense_with(&input).await }
+    };
+    let response = dispense("https://wallet.example.com/done?from=faucet&tx_id=0x00")
+        .await
+        .unwrap();
+    assert_eq!(
+        response.redirect_url,
+        Some(format!(
+            "https://wallet.example.com/done?from=faucet&tx_id=0x{}",
+            response.tx_id
+        ))
+    );
+    let response = dispense("javascript:alert(1)").await.unwrap();
+    assert_eq!(response.redirect_url, None);
+}
+
+/// Receives webhooks, failing the first delivery of each payload.
+fn start_webhook_receiver(deliveries: Arc<Mutex<Vec<(HeaderMap, String)>>>) -> SocketAddr {
+    let receive = move |headers: HeaderMap, body: String| async move {
+        let mut deliveries = deliveries.lock().unwrap();
+        let retried = deliveries
+            .iter()
+            .any(|(delivered, _)| delivered["x-faucet-delivery"] == headers["x-faucet-delivery"]);
+        deliveries.push((headers, body));
+        if retried {
+            axum::http::StatusCode::OK
+        } else {
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener).unwrap().serve(
+            Router::new()
+                .route("/hook", post(receive))
+                .into_make_service(),
+        ),
+    );
+    addr
+}
+
+#[tokio::test]
+async fn notifies_webhooks() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let deliveries = Arc::new(Mutex::new(Vec::new()));
+    let receiver = start_webhook_receiver(deliveries.clone());
+    let dead_letter_file = std::env::temp_dir().join("faucet-test-webhook-dead-letters.jsonl");
+    let _ = std::fs::remove_file(&dead_letter_file);
+    let context = TestContext::new_with_config(&mut rng, |config| Config {
+        // nothing listens on the second endpoint
+        webhook_urls: vec![
+            format!("http://{receiver}/hook"),
+            "http://127.0.0.1:1/hook".to_string(),
+        ],
+        webhook_secret: Some(Secret::new("secret".to_string())),
+        webhook_max_attempts: 2,
+        webhook_dead_letter_file: Some(dead_letter_file.clone()),
+        low_balance_threshold: Some(u64::MAX),
+        ..config
+    })
+    .await;
+
+    let response = context
+        .faucet
+        .dispense(&format!("{recipient_address:#x}"), "")
+        .await
+        .unwrap();
+
+    // the committed dispense and the low balance, each failing once and then retried
+    let deliveries = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let deliveries = deliveries.lock().unwrap().clone();
+            if deliveries.len() >= 4 {
+                return deliveries;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("The webhooks should be delivered");
+
+    let mut payloads = HashMap::new();
+    for (headers, body) in deliveries {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(headers["x-faucet-timestamp"].as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        assert_eq!(
+            headers["x-faucet-signature"].to_str().unwrap(),
+            format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+        );
+        let payload: WebhookPayload = serde_json::from_str(&body).unwrap();
+        payloads.entry(payload.id.clone()).or_insert((payload, 0)).1 += 1;
+    }
+    assert!(payloads.values().all(|(_, deliveries)| *deliveries == 2));
+    let events: Vec<_> = payloads
+        .into_values()
+        .map(|(payload, _)| payload.event)
+        .collect();
+    assert!(events.contains(&WebhookEvent::DispenseCommitted {
+        recipient: format!("{recipient_address:#x}"),
+        tx_id: response.tx_id,
+        amount: response.tokens,
+    }));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, WebhookEvent::LowBalance { .. })));
+
+    // deliveries to the unreachable endpoint end up in the dead-letter file
+    let dead_letters = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let dead_letters = std::fs::read_to_string(&dead_letter_file).unwrap_or_default();
+            if dead_letters.lines().count() >= 2 {
+                return dead_letters;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("Undeliverable webhooks should be dead-lettered");
+    for line in dead_letters.lines() {
+        let letter: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(letter["url"], "http://127.0.0.1:1/hook");
+        assert_eq!(letter["attempts"], 2);
+        serde_json::from_value::<WebhookPayload>(letter["payload"].clone()).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn dead_letters_pending_webhooks_on_shutdown() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let dead_letter_file = std::env::temp_dir().join("faucet-test-shutdown-dead-letters.jsonl");
+    let _ = std::fs::remove_file(&dead_letter_file);
+    let mut context = TestContext::new_with_config(&mut rng, |config| Config {
+        // nothing listens, and the retries would outlast the shutdown timeout
+        webhook_urls: vec!["http://127.0.0.1:1/hook".to_string()],
+        webhook_secret: Some(Secret::new("secret".to_string())),
+        webhook_max_attempts: 100,
+        webhook_dead_letter_file: Some(dead_letter_file.clone()),
+        shutdown_timeout: 2,
+        ..config
+    })
+    .await;
+
+    context
+        .faucet
+        .dispense(&format!("{recipient_address:#x}"), "")
+        .await
+        .unwrap();
+    context.shutdown.take().unwrap().send(()).unwrap();
+    tokio::time::timeout(Duration::from_secs(10), context.server)
+        .await
+        .expect("Shutdown shouldn't wait for the retries")
+        .unwrap()
+        .unwrap();
+
+    let dead_letters = std::fs::read_to_string(&dead_letter_file).unwrap();
+    assert_eq!(dead_letters.lines().count(), 1);
+    let letter: serde_json::Value = serde_json::from_str(dead_letters.trim()).unwrap();
+    let payload: WebhookPayload = serde_json::from_value(letter["payload"].clone()).unwrap();
+    assert!(matches!(
+        payload.event,
+        WebhookEvent::DispenseCommitted { .. }
+    ));
+    assert!(letter["attempts"].as_u64().unwrap() < 100);
+}
+
+#[tokio::test]
+async fn dispense_once_per_day() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let recipient_address_str = format!("{}", &recipient_address);
+    let context = TestContext::new(&mut rng).await;
+
+    let dispense_interval = 24 * 60 * 60;
+    let time_increment = dispense_interval / 6;
+
+    context
+        .faucet
+        .dispense(&recipient_address_str, "")
+        .await
+        .expect("First dispensing request should be successful");
+
+    for _ in 0..5 {
+        context.clock.advance(time_increment);
+
+        let eligibility = context
+            .faucet
+            .eligibility(&recipient_address_str)
             .await
-            .expect("Subsequent dispensing requests should be successfully sent");
+            .unwrap();
+        assert!(!eligibility.eligible);
+        assert_eq!(eligibility.code, Some(ErrorCode::RateLimited));
 
-        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        let error = context
+            .faucet
+            .dispense(&recipient_address_str, "")
+            .await
+            .expect_err("Subsequent dispensing requests should be rate limited");
+
+        assert_eq!(error.code(), Some(ErrorCode::RateLimited));
     }
 
     context.clock.advance(time_increment + 1);
-    let response = reqwest::Client::new()
-        .post(format!("http://{addr}/dispense"))
-        .json(&json!({
-            "captcha": "",
-            "address": recipient_address_str.clone(),
-        }))
-        .send()
+    let eligibility = context
+        .faucet
+        .eligibility(&recipient_address_str)
+        .await
+        .unwrap();
+    assert!(eligibility.eligible);
+
+    context
+        .faucet
+        .dispense(&recipient_address_str, "")
         .await
         .expect("Dispensing requests after the interval should be successful");
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_in_flight_dispenses() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let recipient_address: Address = rng.gen();
+    let recipient_address_str = format!("{}", &recipient_address);
+    let mut context = TestContext::new(&mut rng).await;
+
+    let faucet = context.faucet.clone();
+    let request = tokio::spawn(async move { faucet.dispense(&recipient_address_str, "").await });
+
+    // give the dispense time to reach the faucet before asking it to shut down
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    context.shutdown.take().unwrap().send(()).unwrap();
+
+    request
+        .await
+        .unwrap()
+        .expect("In-flight dispense should complete during shutdown");
+    context
+        .server
+        .await
+        .unwrap()
+        .expect("Server should shut down cleanly");
+
+    let recipient_address: Bech32Address = recipient_address.into();
+    let test_balance: u64 = context
+        .provider
+        .get_coins(
+            &recipient_address,
+            *context.provider.consensus_parameters().base_asset_id(),
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|coin| coin.amount)
+        .sum();
+
+    assert!(test_balance >= context.faucet_config.dispense_amount);
+}
+
+#[tokio::test]
+async fn shutdown_ends_event_streams() {
+    let mut context =
+        TestContext::new_with_config(&mut StdRng::seed_from_u64(42), |config| Config {
+            shutdown_timeout: 60,
+            ..config
+        })
+        .await;
+
+    let mut events = reqwest::get(format!("http://{}/v1/events", context.addr))
+        .await
+        .unwrap();
+    context.shutdown.take().unwrap().send(()).unwrap();
+
+    // well before the shutdown timeout
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while events.chunk().await.unwrap().is_some() {}
+        context.server.await.unwrap().unwrap();
+    })
+    .await
+    .expect("Shutdown shouldn't wait for the event stream");
+}
+
+#[tokio::test]
+async fn wallet_split_and_consolidate() {
+    let context = TestContext::new(&mut StdRng::seed_from_u64(42)).await;
+    let wallet = setup_wallet(&context.faucet_config).await;
+
+    wallet::split(&wallet, 10, 1000)
+        .await
+        .expect("Split should succeed");
+
+    let coins = wallet::spendable_coins(&wallet).await.unwrap();
+    assert_eq!(coins.iter().filter(|coin| coin.amount == 1000).count(), 10);
+
+    let target = coins.len() - 10;
+    let tx_ids = wallet::consolidate(&wallet, target)
+        .await
+        .expect("Consolidation should succeed");
 
-    assert_eq!(response.status(), reqwest::StatusCode::CREATED);
+    assert_eq!(tx_ids.len(), 1);
+    let coins = wallet::spendable_coins(&wallet).await.unwrap();
+    assert_eq!(coins.len(), target);
+    assert!(coins.iter().all(|coin| coin.amount != 1000));
 }
@@ -0,0 +1,9 @@
+use fuel_faucet::openapi::ApiDoc;
+use utoipa::OpenApi;
+
+#[test]
+fn openapi_spec() {
+    // Any change to this snapshot is a change to the HTTP contract that clients rely on
+    let spec = serde_json::to_string_pretty(&ApiDoc::openapi()).unwrap();
+    insta::assert_snapshot!(spec);
+}